@@ -0,0 +1,87 @@
+//! Compares the queue backends of a discrete-event scheduler holding a million pending events.
+//!
+//! Run with `cargo bench --bench queue_backends`.
+//!
+use std::{sync::Arc, time::Instant};
+
+use sample::scheduler::event::EventNotification;
+use sample::scheduler::internal::{DiscreteEventOptions, QueueBackend, SchedulerHandle};
+use sample::scheduler::standard_events::OneShotEvent;
+use sample::time::{ManualClock, SimClock, SimTime};
+use tokio::sync::{mpsc, RwLock};
+
+const EVENTS: u64 = 1_000_000;
+/// The heap scans the whole queue per cancellation, so only a sample of events is cancelled.
+const CANCEL_EVERY: usize = 10_000;
+const DAY_MILLIS: u64 = 86_400_000;
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("failed to build runtime");
+    for backend in [QueueBackend::TimingWheel, QueueBackend::Heap] {
+        runtime.block_on(bench(backend));
+    }
+}
+
+/// Deterministic pseudo-random numbers below `bound`.
+fn next(state: &mut u64, bound: u64) -> u64 {
+    *state = state
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    (*state >> 33) % bound
+}
+
+async fn bench(backend: QueueBackend) {
+    let clock = Arc::new(RwLock::new(ManualClock::default()));
+    clock.write().await.pause();
+    let (sender, mut notifications) = mpsc::unbounded_channel::<EventNotification<ManualClock>>();
+    let handle = SchedulerHandle::new_discrete(
+        Arc::clone(&clock),
+        sender,
+        DiscreteEventOptions {
+            stop_time: Some(SimTime::from_millis(DAY_MILLIS)),
+            ..Default::default()
+        },
+    )
+    .with_queue(backend);
+
+    let mut random = 11;
+    let start = Instant::now();
+    let mut ids = Vec::with_capacity(EVENTS as usize);
+    for _ in 0..EVENTS {
+        let time = SimTime::from_millis(next(&mut random, DAY_MILLIS));
+        ids.push(
+            handle
+                .schedule(OneShotEvent::new("bench", time))
+                .await
+                .unwrap(),
+        );
+    }
+    let scheduled = start.elapsed();
+
+    let start = Instant::now();
+    let mut cancelled = 0;
+    for id in ids.into_iter().step_by(CANCEL_EVERY) {
+        assert_eq!(handle.cancel(id).await.unwrap(), 1);
+        cancelled += 1;
+    }
+    let cancel_time = start.elapsed();
+
+    let start = Instant::now();
+    clock.write().await.resume();
+    let mut fired = 0;
+    while notifications.recv().await.is_some() {
+        fired += 1;
+    }
+    let fire_time = start.elapsed();
+
+    assert_eq!(fired, EVENTS - cancelled);
+    println!(
+        "{backend:?}: schedule {:.0}/s, cancel {:.0}/s, fire {:.0}/s",
+        EVENTS as f64 / scheduled.as_secs_f64(),
+        cancelled as f64 / cancel_time.as_secs_f64(),
+        fired as f64 / fire_time.as_secs_f64(),
+    );
+}
@@ -11,12 +11,41 @@ pub enum ToolboxError {
         #[from]
         source: config::ConfigError,
     },
+    /// Used when a socket or file operation fails
+    #[error("IO error {source}")]
+    Io {
+        /// wrapper for the underlying error source
+        #[from]
+        source: std::io::Error,
+    },
+    /// Used when a message cannot be encoded or decoded
+    #[error("Serialization error {source}")]
+    Serialization {
+        /// wrapper for the underlying error source
+        #[from]
+        source: serde_json::Error,
+    },
     /// Used to indicate a failure when converting types
     #[error("Conversion error {0}")]
     Conversion(
         /// Information about the failure
         String,
     ),
+    /// Used when a time dilation factor is not a positive, finite number
+    #[error("Invalid time dilation {0}, must be positive and finite")]
+    InvalidTimeDilation(f64),
+    /// Used when settings contain invalid values, listing every invalid field
+    #[error("Invalid settings: {}", .0.join("; "))]
+    InvalidSettings(Vec<String>),
+    /// Used when a scheduler refuses to queue an event
+    #[error("Event rejected: {0}")]
+    EventRejected(String),
+    /// Used when an event handler panics, carrying the panic message
+    #[error("Event handler panicked: {0}")]
+    HandlerPanicked(String),
+    /// Used when a shutdown is requested while an earlier one is still waiting for its deadline
+    #[error("A shutdown is already pending")]
+    ShutdownPending,
     /// Actor closed error
     #[error("Unable to communicate with actor {0}")]
     ActorClosed(String),
@@ -28,5 +57,11 @@ impl<T> From<tokio::sync::mpsc::error::SendError<T>> for ToolboxError {
     }
 }
 
+impl From<tokio::sync::oneshot::error::RecvError> for ToolboxError {
+    fn from(value: tokio::sync::oneshot::error::RecvError) -> Self {
+        ToolboxError::ActorClosed(format!("No reply from actor {}", value))
+    }
+}
+
 /// Encapsulates the outcome of an operation that might produce a result of type `T` or an error of type `ToolboxError`.
 pub type ToolboxResult<T = ()> = Result<T, ToolboxError>;
@@ -1,3 +1,4 @@
 pub mod error;
 pub mod scheduler;
+pub mod settings;
 pub mod time;
@@ -1,11 +1,45 @@
 use std::{
     fmt::{Display, Formatter},
-    marker::PhantomData,
+    sync::Arc,
 };
 
 use crate::time::Clock;
 
-/// event to be scheduled
+/// Identifies a single scheduled event. Returned by
+/// [`SchedulerHandle::schedule`](super::internal::SchedulerHandle::schedule) and shared by every
+/// occurrence of a recurring event.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct EventId(pub u64);
+
+impl Display for EventId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// How the scheduler handles occurrences of a recurring event that came due while it was not
+/// able to fire them, for example because the system stalled or the clock jumped forwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MisfirePolicy {
+    /// Fire every missed occurrence, in order.
+    FireAll,
+    /// Fire a single notification for the latest missed occurrence, reporting the number of
+    /// earlier occurrences folded into it in [`EventNotification::missed`].
+    Coalesce,
+    /// Fire the earliest missed occurrence and drop the rest. The series continues at its next
+    /// occurrence after the current time.
+    Skip,
+    /// Fire the earliest missed occurrence and shift the whole series so that the next
+    /// occurrence follows the current time by the usual interval. Requires
+    /// [`Event::with_execution_time`], otherwise behaves like [`MisfirePolicy::Skip`].
+    Delay,
+}
+
+/// event to be scheduled. The scheduler orders events by execution time, then by
+/// [`Event::priority`], then by the order they were scheduled in.
 pub trait Event<T: Clock>: Ord + Send + Sync {
     /// name of event
     fn name(&self) -> &str;
@@ -15,28 +49,170 @@ pub trait Event<T: Clock>: Ord + Send + Sync {
     fn next_time(&self) -> Self;
     /// number of times to execute event
     fn count(&self, new_count: u64) -> Self;
+    /// number of times left to execute event, including this occurrence. The scheduler stops
+    /// re-queuing the event after the last one, updating the count of each occurrence with
+    /// [`Event::count`]. Returns `None` if the event repeats until cancelled.
+    fn remaining(&self) -> Option<u64> {
+        None
+    }
+    /// previous time to execute event. Used to re-arm recurring events when the clock is moved
+    /// backwards. Returns `None` if there is no earlier occurrence.
+    fn previous_time(&self) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+    /// same event moved to execute at `time`. Returns `None` if the event cannot be moved.
+    fn with_execution_time(&self, _time: T::Time) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
+    /// priority among events executing at the same time. Higher priorities fire first.
+    fn priority(&self) -> i32 {
+        0
+    }
+    /// how missed occurrences are handled. Returns `None` to use the clock's default, which
+    /// fires every occurrence for clocks that [fire missed
+    /// occurrences](Clock::fires_missed_occurrences) and skips them otherwise.
+    fn misfire_policy(&self) -> Option<MisfirePolicy> {
+        None
+    }
+    /// tags copied into every notification of the event, used to filter subscriptions
+    fn tags(&self) -> &[String] {
+        &[]
+    }
 }
 
-/// Notification event occured
+/// event delivering a payload of type `P` in every [`EventNotification`]. Every [`Event`]
+/// delivers the empty payload `()`, producing name-only notifications. Implement
+/// `PayloadEvent<T, Self>` to deliver the fired event itself, and create the scheduler as a
+/// [`SchedulerHandle<T, E, P>`](super::internal::SchedulerHandle).
+pub trait PayloadEvent<T: Clock, P>: Event<T> {
+    /// payload delivered with the notification of this occurrence
+    fn payload(&self) -> P;
+}
+
+impl<T: Clock, E: Event<T>> PayloadEvent<T, ()> for E {
+    fn payload(&self) {}
+}
+
+/// Notification event occured, carrying the [payload](PayloadEvent) of the event. Events
+/// without a payload produce name-only `EventNotification<T>`s.
+#[derive(Debug)]
+pub struct EventNotification<T: Clock, P = ()> {
+    pub id: EventId,
+    /// Shared by every notification of the same schedule.
+    pub name: Arc<str>,
+    pub time: T::Time,
+    pub occurrence: u64,
+    pub missed: u64,
+    pub is_final: bool,
+    pub tags: Vec<String>,
+    pub payload: P,
+}
+
+impl<T: Clock, P: Clone> Clone for EventNotification<T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            name: self.name.clone(),
+            time: self.time,
+            occurrence: self.occurrence,
+            missed: self.missed,
+            is_final: self.is_final,
+            tags: self.tags.clone(),
+            payload: self.payload.clone(),
+        }
+    }
+}
+
+impl<T: Clock, P> EventNotification<T, P> {
+    /// id the event was scheduled under
+    pub fn id(&self) -> EventId {
+        self.id
+    }
+
+    /// name of event that occured
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// time the event occured
+    pub fn time(&self) -> T::Time {
+        self.time
+    }
+
+    /// zero-based index of this occurrence of the event
+    pub fn occurrence(&self) -> u64 {
+        self.occurrence
+    }
+
+    /// number of missed occurrences coalesced into this notification
+    pub fn missed(&self) -> u64 {
+        self.missed
+    }
+
+    /// whether this is the last occurrence, after which the event is no longer scheduled
+    pub fn is_final(&self) -> bool {
+        self.is_final
+    }
+
+    /// tags of the event that occured
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// payload of the event that occured
+    pub fn payload(&self) -> &P {
+        &self.payload
+    }
+
+    /// take the payload of the event that occured
+    pub fn into_payload(self) -> P {
+        self.payload
+    }
+}
+
+/// An event waiting in a scheduler's queue.
 #[derive(Clone, Debug)]
-pub struct EventNotification<T: Clock> {
+pub struct PendingEvent<T: Clock> {
+    pub id: EventId,
     pub name: String,
     pub time: T::Time,
 }
 
-impl<T: Clock> EventNotification<T> {
-    /// name of event that occured
+impl<T: Clock> PendingEvent<T> {
+    /// id the event was scheduled under
+    pub fn id(&self) -> EventId {
+        self.id
+    }
+
+    /// name of the pending event
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
 
-    /// time the event occured
+    /// time the event will next execute
     pub fn time(&self) -> T::Time {
         self.time
     }
 }
 
-impl<T: Clock> Display for EventNotification<T>
+/// An event that was still queued when its scheduler shut down.
+#[derive(Debug)]
+pub struct UnfiredEvent<E> {
+    /// id the event was scheduled under
+    pub id: EventId,
+    /// the next occurrence that would have fired
+    pub event: E,
+    /// zero-based index of that occurrence
+    pub occurrence: u64,
+}
+
+impl<T: Clock, P> Display for EventNotification<T, P>
 where
     T::Time: Display,
 {
@@ -0,0 +1,435 @@
+//! This module contains async handlers that a scheduler runs when events fire.
+//!
+//! Handlers are registered on a [`SchedulerHandle`](super::internal::SchedulerHandle) for a
+//! single event or for every event with a given name. Each invocation runs in its own task, so a
+//! slow, failing or panicking handler never stalls the scheduler. Failures are reported on the
+//! channel returned by
+//! [`SchedulerHandle::handler_failures`](super::internal::SchedulerHandle::handler_failures).
+//!
+use std::{
+    fmt::{Display, Formatter},
+    future::Future,
+    num::NonZeroUsize,
+    pin::Pin,
+    sync::Arc,
+};
+
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::{
+    error::{ToolboxError, ToolboxResult},
+    time::Clock,
+};
+
+use super::event::{EventId, EventNotification};
+use super::internal::DrainTracker;
+
+/// Future returned by an [`EventHandler`].
+pub type HandlerFuture = Pin<Box<dyn Future<Output = ToolboxResult> + Send>>;
+
+/// Async callback invoked with the notification of every matching event that fires.
+///
+/// Closures taking an [`EventNotification`] and returning a future are handlers, so
+/// `|notification| async move { ...; Ok(()) }` can be registered directly.
+pub trait EventHandler<T: Clock, P = ()>: Send + Sync {
+    fn handle(&self, notification: EventNotification<T, P>) -> HandlerFuture;
+}
+
+impl<T, P, F, Fut> EventHandler<T, P> for F
+where
+    T: Clock,
+    F: Fn(EventNotification<T, P>) -> Fut + Send + Sync,
+    Fut: Future<Output = ToolboxResult> + Send + 'static,
+{
+    fn handle(&self, notification: EventNotification<T, P>) -> HandlerFuture {
+        Box::pin(self(notification))
+    }
+}
+
+/// The events a handler is invoked for.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HandlerTarget {
+    /// Every occurrence of the event scheduled under this ID.
+    Event(EventId),
+    /// Every event with this name.
+    Name(String),
+}
+
+impl HandlerTarget {
+    fn matches<T: Clock, P>(&self, notification: &EventNotification<T, P>) -> bool {
+        match self {
+            HandlerTarget::Event(id) => notification.id == *id,
+            HandlerTarget::Name(name) => notification.name() == name,
+        }
+    }
+}
+
+/// Identifies a registered handler so that it can be removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HandlerId(pub u64);
+
+impl Display for HandlerId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "handler #{}", self.0)
+    }
+}
+
+/// A handler that returned an error or panicked.
+pub struct HandlerFailure<T: Clock, P = ()> {
+    /// The handler that failed.
+    pub handler: HandlerId,
+    /// The notification the handler was invoked with.
+    pub notification: EventNotification<T, P>,
+    /// The error returned, or [`ToolboxError::HandlerPanicked`] if the handler panicked.
+    pub error: ToolboxError,
+}
+
+struct RegisteredHandler<T: Clock, P> {
+    id: HandlerId,
+    target: HandlerTarget,
+    handler: Arc<dyn EventHandler<T, P>>,
+}
+
+/// Handlers owned by the scheduler actor.
+pub(crate) struct HandlerRegistry<T: Clock, P> {
+    handlers: Vec<RegisteredHandler<T, P>>,
+    next_id: u64,
+    permits: Option<Arc<Semaphore>>,
+    failures: Option<mpsc::UnboundedSender<HandlerFailure<T, P>>>,
+}
+
+impl<T: Clock + 'static, P: Clone + Send + Sync + 'static> HandlerRegistry<T, P> {
+    /// Create a registry running at most `concurrency` handlers at once, or any number if unset.
+    pub fn new(concurrency: Option<NonZeroUsize>) -> Self {
+        Self {
+            handlers: Vec::new(),
+            next_id: 0,
+            permits: concurrency.map(|permits| Arc::new(Semaphore::new(permits.get()))),
+            failures: None,
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        target: HandlerTarget,
+        handler: Arc<dyn EventHandler<T, P>>,
+    ) -> HandlerId {
+        let id = HandlerId(self.next_id);
+        self.next_id += 1;
+        self.handlers.push(RegisteredHandler {
+            id,
+            target,
+            handler,
+        });
+        id
+    }
+
+    /// Remove a handler. Returns `true` if it was registered.
+    pub fn remove(&mut self, id: HandlerId) -> bool {
+        let before = self.handlers.len();
+        self.handlers.retain(|registered| registered.id != id);
+        self.handlers.len() != before
+    }
+
+    /// Report failures on a new channel, replacing any previous one.
+    pub fn subscribe_failures(&mut self) -> mpsc::UnboundedReceiver<HandlerFailure<T, P>> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.failures = Some(sender);
+        receiver
+    }
+
+    /// Spawn every handler matching `notification`. Each invocation counts as an outstanding
+    /// notification on `drain` until it completes.
+    pub fn dispatch(&self, notification: &EventNotification<T, P>, drain: Option<&DrainTracker>) {
+        for registered in self
+            .handlers
+            .iter()
+            .filter(|registered| registered.target.matches(notification))
+        {
+            let id = registered.id;
+            let handler = Arc::clone(&registered.handler);
+            let notification = notification.clone();
+            let permits = self.permits.clone();
+            let failures = self.failures.clone();
+            let drain = drain.cloned();
+            if let Some(drain) = drain.as_ref() {
+                drain.sent();
+            }
+
+            tokio::spawn(async move {
+                let _permit = match permits {
+                    Some(permits) => permits.acquire_owned().await.ok(),
+                    None => None,
+                };
+                // Run the handler in its own task so that a panic is caught and reported.
+                let invocation = notification.clone();
+                let result = tokio::spawn(async move { handler.handle(invocation).await })
+                    .await
+                    .unwrap_or_else(|err| Err(ToolboxError::HandlerPanicked(panic_message(err))));
+                if let (Err(error), Some(failures)) = (result, failures) {
+                    _ = failures.send(HandlerFailure {
+                        handler: id,
+                        notification,
+                        error,
+                    });
+                }
+                if let Some(drain) = drain {
+                    drain.done();
+                }
+            });
+        }
+    }
+}
+
+fn panic_message(err: tokio::task::JoinError) -> String {
+    match err.try_into_panic() {
+        Ok(payload) => payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "handler panicked".to_string()),
+        Err(err) => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod handler_tests {
+    use std::{
+        cmp::Ordering as CmpOrdering,
+        num::NonZeroUsize,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use tokio::sync::{mpsc, RwLock};
+
+    use crate::error::ToolboxError;
+    use crate::scheduler::event::{Event, EventNotification};
+    use crate::scheduler::handler::HandlerTarget;
+    use crate::scheduler::internal::{SchedulerHandle, SchedulerOptions};
+    use crate::time::{Clock, ManualClock, SimDuration, SimTime};
+
+    #[derive(PartialEq, Eq)]
+    struct Once(&'static str, SimTime);
+
+    impl PartialOrd for Once {
+        fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Once {
+        fn cmp(&self, other: &Self) -> CmpOrdering {
+            self.1.cmp(&other.1).then_with(|| self.0.cmp(other.0))
+        }
+    }
+
+    impl Event<ManualClock> for Once {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn execution_time(&self) -> SimTime {
+            self.1
+        }
+
+        fn next_time(&self) -> Self {
+            Once(self.0, self.1)
+        }
+
+        fn count(&self, _new_count: u64) -> Self {
+            Once(self.0, self.1)
+        }
+    }
+
+    type Handle = SchedulerHandle<ManualClock, Once>;
+
+    fn scheduler(
+        options: SchedulerOptions,
+    ) -> (
+        Arc<RwLock<ManualClock>>,
+        Handle,
+        mpsc::UnboundedReceiver<EventNotification<ManualClock>>,
+    ) {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::with_options(Arc::clone(&clock), sender, options);
+        (clock, handle, receiver)
+    }
+
+    #[tokio::test]
+    async fn handlers_run_for_matching_id_or_name() {
+        let (clock, handle, mut notifications) = scheduler(SchedulerOptions::default());
+        let (fired, mut invocations) = mpsc::unbounded_channel();
+
+        let a = handle
+            .schedule(Once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        handle
+            .schedule(Once("b", SimTime::from_seconds(2)))
+            .await
+            .unwrap();
+        handle
+            .schedule(Once("b", SimTime::from_seconds(3)))
+            .await
+            .unwrap();
+
+        let sender = fired.clone();
+        handle
+            .add_handler(
+                HandlerTarget::Event(a),
+                move |notification: EventNotification<ManualClock>| {
+                    let sender = sender.clone();
+                    async move {
+                        _ = sender.send(format!("id {}", notification.name()));
+                        Ok(())
+                    }
+                },
+            )
+            .await
+            .unwrap();
+        let by_name = handle
+            .add_handler(
+                HandlerTarget::Name("b".to_string()),
+                move |notification: EventNotification<ManualClock>| {
+                    let sender = fired.clone();
+                    async move {
+                        _ = sender.send(format!("name {}", notification.time().as_seconds()));
+                        Ok(())
+                    }
+                },
+            )
+            .await
+            .unwrap();
+
+        clock.write().await.advance_by(SimDuration::seconds(2));
+        let mut received = vec![
+            invocations.recv().await.unwrap(),
+            invocations.recv().await.unwrap(),
+        ];
+        received.sort();
+        assert_eq!(received, ["id a", "name 2"]);
+
+        assert!(handle.remove_handler(by_name).await.unwrap());
+        assert!(!handle.remove_handler(by_name).await.unwrap());
+        clock.write().await.advance_by(SimDuration::seconds(1));
+        while notifications.recv().await.unwrap().time() < SimTime::from_seconds(3) {}
+        tokio::task::yield_now().await;
+        assert!(invocations.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn handler_errors_and_panics_are_reported() {
+        let (clock, handle, mut notifications) = scheduler(SchedulerOptions::default());
+        let mut failures = handle.handler_failures().await.unwrap();
+
+        handle
+            .add_handler(
+                HandlerTarget::Name("fails".to_string()),
+                |_: EventNotification<ManualClock>| async {
+                    Err(ToolboxError::Conversion("bad payload".to_string()))
+                },
+            )
+            .await
+            .unwrap();
+        let panics = handle
+            .add_handler(
+                HandlerTarget::Name("panics".to_string()),
+                |_: EventNotification<ManualClock>| async { panic!("handler blew up") },
+            )
+            .await
+            .unwrap();
+
+        handle
+            .schedule(Once("fails", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        handle
+            .schedule(Once("panics", SimTime::from_seconds(2)))
+            .await
+            .unwrap();
+        clock.write().await.advance_by(SimDuration::seconds(2));
+
+        let mut reported = Vec::new();
+        for _ in 0..2 {
+            let failure = failures.recv().await.unwrap();
+            reported.push((
+                failure.notification.name().to_string(),
+                failure.handler,
+                failure.error,
+            ));
+        }
+        reported.sort_by(|a, b| a.0.cmp(&b.0));
+        assert!(matches!(reported[0].2, ToolboxError::Conversion(_)));
+        assert_eq!(reported[1].1, panics);
+        assert!(
+            matches!(&reported[1].2, ToolboxError::HandlerPanicked(message) if message == "handler blew up")
+        );
+
+        // The scheduler keeps running after a handler panics.
+        handle
+            .schedule(Once("after", SimTime::from_seconds(3)))
+            .await
+            .unwrap();
+        clock.write().await.advance_by(SimDuration::seconds(1));
+        let names: Vec<_> = [
+            notifications.recv().await.unwrap(),
+            notifications.recv().await.unwrap(),
+            notifications.recv().await.unwrap(),
+        ]
+        .iter()
+        .map(|notification| notification.name().to_string())
+        .collect();
+        assert_eq!(names, ["fails", "panics", "after"]);
+    }
+
+    #[tokio::test]
+    async fn handler_concurrency_is_limited() {
+        let (clock, handle, _notifications) = scheduler(SchedulerOptions {
+            handler_concurrency: NonZeroUsize::new(1),
+            ..Default::default()
+        });
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (finished, mut completions) = mpsc::unbounded_channel();
+
+        let (running_count, peak_count) = (Arc::clone(&running), Arc::clone(&peak));
+        handle
+            .add_handler(
+                HandlerTarget::Name("work".to_string()),
+                move |_: EventNotification<ManualClock>| {
+                    let (running, peak, finished) = (
+                        Arc::clone(&running_count),
+                        Arc::clone(&peak_count),
+                        finished.clone(),
+                    );
+                    async move {
+                        let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now_running, Ordering::SeqCst);
+                        tokio::task::yield_now().await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        _ = finished.send(());
+                        Ok(())
+                    }
+                },
+            )
+            .await
+            .unwrap();
+
+        for seconds in 1..=3 {
+            handle
+                .schedule(Once("work", SimTime::from_seconds(seconds)))
+                .await
+                .unwrap();
+        }
+        clock.write().await.advance_by(SimDuration::seconds(3));
+        for _ in 0..3 {
+            completions.recv().await.unwrap();
+        }
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert_eq!(clock.read().await.now(), SimTime::from_seconds(3));
+    }
+}
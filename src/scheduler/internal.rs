@@ -1,132 +1,863 @@
-use std::{cmp::Reverse, collections::BinaryHeap, marker::PhantomData, sync::Arc};
+use std::{
+    future::Future,
+    marker::PhantomData,
+    num::NonZeroUsize,
+    ops::{Add, Bound, RangeBounds},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
-use tokio::sync::{mpsc, Notify, RwLock};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{
+    sync::{mpsc, oneshot, watch, Notify, RwLock},
+    task::JoinHandle,
+};
 
-use crate::{error::ToolboxError, time::Clock};
+use crate::{
+    error::{ToolboxError, ToolboxResult},
+    time::{Clock, ClockChanges, DiscreteClock, TickTime},
+};
 
-use super::event::{Event, EventNotification};
+use super::event::{
+    Event, EventId, EventNotification, MisfirePolicy, PayloadEvent, PendingEvent, UnfiredEvent,
+};
+use super::handler::{EventHandler, HandlerFailure, HandlerId, HandlerRegistry, HandlerTarget};
+use super::notification::NotificationSender;
+use super::persistence::{Journal, NextOccurrence, QueueJournal, Record, RecoveryPolicy};
+use super::queue::{EventQueue, ScheduledEvent};
+use super::shutdown::{ExitReason, ShutdownMode, ShutdownReport};
+use super::subscription::{NotificationFilter, Subscriptions};
 
-pub enum SchedulerCommand<E> {
-    Schedule(E),
-    Cancel { name: String },
+/// Commands sent to the scheduler actor. Every command except `Stop` and `SetQueue` carries a
+/// `reply` that the actor answers once the command has been applied, or for `Shutdown` once the
+/// actor is about to exit.
+pub enum SchedulerCommand<T: Clock, E: Event<T>, P = ()> {
+    /// Queue `event` under `id`, replying whether it was accepted.
+    Schedule {
+        id: EventId,
+        event: E,
+        reply: oneshot::Sender<ToolboxResult>,
+    },
+    /// Cancel the event scheduled under `id`, including future occurrences. Replies with the
+    /// number of events removed.
+    Cancel {
+        id: EventId,
+        reply: oneshot::Sender<usize>,
+    },
+    /// Cancel every event named `name`. Replies with the number of events removed.
+    CancelByName {
+        name: String,
+        reply: oneshot::Sender<usize>,
+    },
+    /// Replace the event scheduled under `id`, keeping its ID and occurrence count.
+    Replace {
+        id: EventId,
+        event: E,
+        reply: oneshot::Sender<ToolboxResult<Modification<T::Time>>>,
+    },
+    /// Move the event scheduled under `id` to the time returned by `time`, which is called with
+    /// its current execution time.
+    Reschedule {
+        id: EventId,
+        time: RescheduleFn<T::Time>,
+        reply: oneshot::Sender<ToolboxResult<Modification<T::Time>>>,
+    },
+    /// Reply with the next execution time of the event scheduled under `id`.
+    ScheduledTime {
+        id: EventId,
+        reply: oneshot::Sender<Option<T::Time>>,
+    },
+    /// Invoke `handler` whenever an event matching `target` fires. Replies with the ID of the
+    /// registration.
+    AddHandler {
+        target: HandlerTarget,
+        handler: Arc<dyn EventHandler<T, P>>,
+        reply: oneshot::Sender<HandlerId>,
+    },
+    /// Remove a handler, replying whether it was registered.
+    RemoveHandler {
+        id: HandlerId,
+        reply: oneshot::Sender<bool>,
+    },
+    /// Reply with a receiver for handler failures, replacing any previous receiver.
+    HandlerFailures {
+        reply: oneshot::Sender<mpsc::UnboundedReceiver<HandlerFailure<T, P>>>,
+    },
+    /// Deliver notifications matching `filter` to `sender` until its receiver is dropped.
+    Subscribe {
+        filter: NotificationFilter<T, P>,
+        sender: NotificationSender<T, P>,
+        drain: bool,
+        reply: oneshot::Sender<()>,
+    },
+    /// Reply with the number of subscribers.
+    SubscriberCount { reply: oneshot::Sender<usize> },
+    /// Reply with the number of pending events.
+    PendingCount { reply: oneshot::Sender<usize> },
+    /// Reply with the execution time of the earliest pending event.
+    NextExecutionTime {
+        reply: oneshot::Sender<Option<T::Time>>,
+    },
+    /// Reply with the pending events executing within `range`, earliest first.
+    Pending {
+        range: (Bound<T::Time>, Bound<T::Time>),
+        reply: oneshot::Sender<Vec<PendingEvent<T>>>,
+    },
+    /// Stop according to `mode`, replying with the events that never fired.
+    Shutdown {
+        mode: ShutdownMode<T::Time>,
+        reply: oneshot::Sender<ToolboxResult<Vec<UnfiredEvent<E>>>>,
+    },
+    /// Move the pending events into `backend`, placing times on a timing wheel by their `tick`.
+    /// Sent by [`SchedulerHandle::with_queue`] without a reply.
+    SetQueue {
+        backend: QueueBackend,
+        tick: fn(&T::Time) -> u64,
+    },
+    /// Stop the scheduler, dropping pending events. A journal keeps them for recovery.
     Stop,
 }
 
-pub struct SchedulerHandle<T: Clock, E: Event<T>> {
-    command_sender: mpsc::UnboundedSender<SchedulerCommand<E>>,
+/// Computes the new execution time of a rescheduled event from its current one.
+pub type RescheduleFn<Time> = Box<dyn FnOnce(Time) -> Time + Send>;
+
+/// Outcome of a command that modifies a pending event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modification<Time> {
+    /// The event was modified and next executes at this time.
+    Applied(Time),
+    /// Nothing was modified because the event is no longer pending: its last occurrence has
+    /// already fired, or it was cancelled.
+    AlreadyFired,
+    /// Nothing was modified because the event cannot be moved, as
+    /// [`Event::with_execution_time`] returned `None`.
+    Unsupported,
+}
+
+/// How a scheduler treats events that were passed over when the clock jumped forwards with
+/// [`SimClock::seek`](crate::time::SimClock::seek).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeekPolicy {
+    /// Fire every occurrence that was passed over, in order.
+    #[default]
+    Fire,
+    /// Drop the occurrences that were passed over. Recurring events continue from their first
+    /// occurrence at or after the new time.
+    Skip,
+}
+
+/// Which structure a scheduler keeps its pending events in, chosen with
+/// [`SchedulerHandle::with_queue`]. Both fire events in the same order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueBackend {
+    /// A binary heap. Cancelling, replacing or looking up an event scans the whole queue.
+    #[default]
+    Heap,
+    /// A hierarchical timing wheel with millisecond slots. Scheduling, cancelling, replacing and
+    /// looking up an event take amortized constant time, suiting queues of many thousands of
+    /// events. Requires a clock whose time implements [`TickTime`].
+    TimingWheel,
+}
+
+/// Options for a [`Scheduler`] that waits for events to come due on its clock.
+#[derive(Debug, Clone, Default)]
+pub struct SchedulerOptions {
+    /// How events passed over by a forward seek are handled. Backward seeks always re-arm
+    /// recurring events using [`Event::previous_time`].
+    pub seek_policy: SeekPolicy,
+    /// Maximum number of event handlers running at once. Unlimited when unset.
+    pub handler_concurrency: Option<NonZeroUsize>,
+}
+
+/// Options for running a [`Scheduler`] as a discrete-event simulation.
+pub struct DiscreteEventOptions<Time> {
+    /// Stop once the next event would execute after this time. The clock is left at the stop time.
+    pub stop_time: Option<Time>,
+    /// Wait for consumers, including subscribers added with
+    /// [`SchedulerHandle::subscribe_drained`], to mark every notification as processed before
+    /// advancing the clock.
+    pub drain: Option<DrainTracker>,
+    /// Maximum number of event handlers running at once. Unlimited when unset. Handlers count
+    /// towards `drain`, so the clock only advances once they complete.
+    pub handler_concurrency: Option<NonZeroUsize>,
+}
+
+impl<Time> Default for DiscreteEventOptions<Time> {
+    fn default() -> Self {
+        Self {
+            stop_time: None,
+            drain: None,
+            handler_concurrency: None,
+        }
+    }
+}
+
+/// Counts notifications that consumers have not finished processing. Consumers call
+/// [`DrainTracker::done`] once for every notification they receive.
+#[derive(Clone, Default)]
+pub struct DrainTracker {
+    inner: Arc<DrainState>,
+}
+
+#[derive(Default)]
+struct DrainState {
+    outstanding: AtomicUsize,
+    drained: Notify,
+}
+
+impl DrainTracker {
+    /// Create a tracker with no outstanding notifications.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark a single notification as processed.
+    pub fn done(&self) {
+        let previous = self
+            .inner
+            .outstanding
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                Some(count.saturating_sub(1))
+            })
+            .unwrap_or_default();
+        if previous <= 1 {
+            self.inner.drained.notify_waiters();
+        }
+    }
+
+    /// Number of notifications sent but not yet processed.
+    pub fn outstanding(&self) -> usize {
+        self.inner.outstanding.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn sent(&self) {
+        self.inner.outstanding.fetch_add(1, Ordering::AcqRel);
+    }
+
+    async fn drained(&self) {
+        loop {
+            let notified = self.inner.drained.notified();
+            if self.outstanding() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+pub struct SchedulerHandle<T: Clock, E: Event<T>, P = ()> {
+    command_sender: mpsc::UnboundedSender<SchedulerCommand<T, E, P>>,
     clock: Arc<RwLock<T>>,
+    next_id: AtomicU64,
+    /// Why the actor task exited, set by a task awaiting it.
+    exit: watch::Receiver<Option<ExitReason>>,
     _phantom: PhantomData<E>,
 }
 
-impl<T: Clock + Sync + Send, E: Event<T> + 'static> SchedulerHandle<T, E> {
-    pub fn new(
+impl<T, E, P> SchedulerHandle<T, E, P>
+where
+    T: Clock + Sync + Send + 'static,
+    E: PayloadEvent<T, P> + 'static,
+    P: Clone + Send + Sync + 'static,
+{
+    pub fn new(clock: Arc<RwLock<T>>, event_sender: impl Into<NotificationSender<T, P>>) -> Self {
+        Self::with_options(clock, event_sender, SchedulerOptions::default())
+    }
+
+    pub fn with_options(
         clock: Arc<RwLock<T>>,
-        event_sender: mpsc::UnboundedSender<EventNotification<T>>,
+        event_sender: impl Into<NotificationSender<T, P>>,
+        options: SchedulerOptions,
     ) -> Self {
         let (sender, receiver) = mpsc::unbounded_channel();
         let clock_clone = Arc::clone(&clock);
-        let mut actor = Scheduler::new(receiver, event_sender, clock_clone);
+        let mut actor = Scheduler::new(receiver, event_sender, clock_clone).with_options(options);
 
-        tokio::spawn(async move {
-            _ = actor.run().await;
+        let task = tokio::spawn(async move {
+            actor
+                .run()
+                .await
+                .unwrap_or_else(|err| ExitReason::Failed(err.to_string()))
         });
 
+        Self::from_parts(sender, clock, task, 0)
+    }
+
+    fn from_parts(
+        command_sender: mpsc::UnboundedSender<SchedulerCommand<T, E, P>>,
+        clock: Arc<RwLock<T>>,
+        task: JoinHandle<ExitReason>,
+        next_id: u64,
+    ) -> Self {
+        let (exit_sender, exit) = watch::channel(None);
+        tokio::spawn(async move {
+            let reason = task
+                .await
+                .unwrap_or_else(|err| ExitReason::Aborted(err.to_string()));
+            _ = exit_sender.send(Some(reason));
+        });
         Self {
-            command_sender: sender,
+            command_sender,
             clock,
+            next_id: AtomicU64::new(next_id),
+            exit,
             _phantom: PhantomData,
         }
     }
 
-    pub fn schedule(&self, event: E) -> Result<(), mpsc::error::SendError<SchedulerCommand<E>>> {
-        self.command_sender.send(SchedulerCommand::Schedule(event))
+    /// Send a command built around a reply channel and wait for the actor to answer.
+    async fn request<R>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<R>) -> SchedulerCommand<T, E, P>,
+    ) -> ToolboxResult<R> {
+        let (reply, response) = oneshot::channel();
+        self.command_sender.send(command(reply))?;
+        Ok(response.await?)
+    }
+
+    /// Schedule an event once the actor has accepted it, returning the ID that identifies it in
+    /// notifications and commands. Real time schedulers fire events scheduled in the past as
+    /// soon as they are accepted.
+    ///
+    /// # Errors
+    /// [`ToolboxError::EventRejected`] if a discrete-event scheduler has already moved its clock
+    /// past the event, or [`ToolboxError::ActorClosed`] if the scheduler is no longer running.
+    pub async fn schedule(&self, event: E) -> ToolboxResult<EventId> {
+        let id = EventId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.request(|reply| SchedulerCommand::Schedule { id, event, reply })
+            .await??;
+        Ok(id)
     }
 
     pub async fn now(&self) -> T::Time {
         self.clock.read().await.now()
     }
 
+    /// Cancel the event scheduled under `id`, including future occurrences of recurring events.
+    ///
+    /// # Returns
+    /// The number of events removed, zero if the event already fired or was cancelled.
+    pub async fn cancel(&self, id: EventId) -> ToolboxResult<usize> {
+        self.request(|reply| SchedulerCommand::Cancel { id, reply })
+            .await
+    }
+
+    /// Cancel every pending event named `name`.
+    ///
+    /// # Returns
+    /// The number of events removed.
+    pub async fn cancel_by_name(&self, name: &str) -> ToolboxResult<usize> {
+        let name = name.to_string();
+        self.request(|reply| SchedulerCommand::CancelByName { name, reply })
+            .await
+    }
+
+    /// Cancel every pending event named `name` without waiting for the scheduler to apply it.
+    ///
+    /// # Errors
+    /// The unsent command if the scheduler is no longer running.
+    #[deprecated(note = "use `cancel_by_name`, which waits for the cancellation to be applied")]
     pub fn cancel_scheduled_event(
         &self,
         name: &str,
-    ) -> Result<(), mpsc::error::SendError<SchedulerCommand<E>>> {
-        self.command_sender.send(SchedulerCommand::Cancel {
+    ) -> Result<(), mpsc::error::SendError<SchedulerCommand<T, E, P>>> {
+        let (reply, _) = oneshot::channel();
+        self.command_sender.send(SchedulerCommand::CancelByName {
             name: name.to_string(),
+            reply,
+        })
+    }
+
+    /// Replace the event scheduled under `id` with `event`. Notifications for the replacement
+    /// carry the same ID and continue its occurrence count.
+    ///
+    /// # Returns
+    /// [`Modification::AlreadyFired`] if the event is no longer pending, in which case nothing
+    /// is scheduled.
+    ///
+    /// # Errors
+    /// [`ToolboxError::EventRejected`] if a discrete-event scheduler has already moved its clock
+    /// past the replacement.
+    pub async fn replace(&self, id: EventId, event: E) -> ToolboxResult<Modification<T::Time>> {
+        self.request(|reply| SchedulerCommand::Replace { id, event, reply })
+            .await?
+    }
+
+    /// Move the next occurrence of the event scheduled under `id` to `time` using
+    /// [`Event::with_execution_time`]. Later occurrences of recurring events follow on from the
+    /// new time.
+    ///
+    /// # Returns
+    /// [`Modification::AlreadyFired`] if the event is no longer pending, or
+    /// [`Modification::Unsupported`] if it cannot be moved.
+    ///
+    /// # Errors
+    /// [`ToolboxError::EventRejected`] if a discrete-event scheduler has already moved its clock
+    /// past `time`.
+    pub async fn reschedule(
+        &self,
+        id: EventId,
+        time: T::Time,
+    ) -> ToolboxResult<Modification<T::Time>> {
+        let time = Box::new(move |_| time);
+        self.request(|reply| SchedulerCommand::Reschedule { id, time, reply })
+            .await?
+    }
+
+    /// Delay the next occurrence of the event scheduled under `id` by `by`, for example a
+    /// [`SimDuration`](crate::time::SimDuration). The delay is applied to the execution time
+    /// the event has when the scheduler receives the command. See
+    /// [`SchedulerHandle::reschedule`].
+    pub async fn postpone<D>(&self, id: EventId, by: D) -> ToolboxResult<Modification<T::Time>>
+    where
+        T::Time: Add<D, Output = T::Time>,
+        D: Send + 'static,
+    {
+        let time = Box::new(move |time| time + by);
+        self.request(|reply| SchedulerCommand::Reschedule { id, time, reply })
+            .await?
+    }
+
+    /// Next execution time of the event scheduled under `id`, or `None` if it is no longer
+    /// pending.
+    pub async fn scheduled_time(&self, id: EventId) -> ToolboxResult<Option<T::Time>> {
+        self.request(|reply| SchedulerCommand::ScheduledTime { id, reply })
+            .await
+    }
+
+    /// Invoke `handler` in its own task whenever an event matching `target` fires. Handler
+    /// errors and panics are reported through [`SchedulerHandle::handler_failures`].
+    ///
+    /// # Returns
+    /// The ID used to remove the handler with [`SchedulerHandle::remove_handler`].
+    pub async fn add_handler(
+        &self,
+        target: HandlerTarget,
+        handler: impl EventHandler<T, P> + 'static,
+    ) -> ToolboxResult<HandlerId> {
+        let handler = Arc::new(handler);
+        self.request(|reply| SchedulerCommand::AddHandler {
+            target,
+            handler,
+            reply,
+        })
+        .await
+    }
+
+    /// Stop invoking a handler. Returns `false` if it was not registered.
+    pub async fn remove_handler(&self, id: HandlerId) -> ToolboxResult<bool> {
+        self.request(|reply| SchedulerCommand::RemoveHandler { id, reply })
+            .await
+    }
+
+    /// Receive a [`HandlerFailure`] for every handler that returns an error or panics. Only the
+    /// most recently returned receiver gets failures.
+    pub async fn handler_failures(
+        &self,
+    ) -> ToolboxResult<mpsc::UnboundedReceiver<HandlerFailure<T, P>>> {
+        self.request(|reply| SchedulerCommand::HandlerFailures { reply })
+            .await
+    }
+
+    /// Receive every notification matching `filter` in addition to the scheduler's own
+    /// `event_sender`. Drop the receiver to unsubscribe.
+    pub async fn subscribe(
+        &self,
+        filter: NotificationFilter<T, P>,
+    ) -> ToolboxResult<mpsc::UnboundedReceiver<EventNotification<T, P>>> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribe_with(filter, sender).await?;
+        Ok(receiver)
+    }
+
+    /// Deliver every notification matching `filter` to `sender`, for example the sender half of
+    /// a [`bounded_channel`](super::notification::bounded_channel). The subscription ends once
+    /// the receiver is dropped. A subscriber whose channel blocks on overflow stalls the
+    /// scheduler until it makes room.
+    pub async fn subscribe_with(
+        &self,
+        filter: NotificationFilter<T, P>,
+        sender: impl Into<NotificationSender<T, P>>,
+    ) -> ToolboxResult {
+        let sender = sender.into();
+        self.request(|reply| SchedulerCommand::Subscribe {
+            filter,
+            sender,
+            drain: false,
+            reply,
+        })
+        .await
+    }
+
+    /// Like [`subscribe_with`](Self::subscribe_with), but the subscriber takes part in the
+    /// [`drain`](DiscreteEventOptions::drain) of a discrete-event scheduler: the clock only
+    /// advances once it has called [`DrainTracker::done`] for every notification delivered.
+    pub async fn subscribe_drained(
+        &self,
+        filter: NotificationFilter<T, P>,
+        sender: impl Into<NotificationSender<T, P>>,
+    ) -> ToolboxResult {
+        let sender = sender.into();
+        self.request(|reply| SchedulerCommand::Subscribe {
+            filter,
+            sender,
+            drain: true,
+            reply,
+        })
+        .await
+    }
+
+    /// Number of subscribers. A subscriber whose receiver was dropped is counted until the
+    /// scheduler next tries to deliver to it.
+    pub async fn subscriber_count(&self) -> ToolboxResult<usize> {
+        self.request(|reply| SchedulerCommand::SubscriberCount { reply })
+            .await
+    }
+
+    /// Number of events waiting to fire.
+    pub async fn pending_count(&self) -> ToolboxResult<usize> {
+        self.request(|reply| SchedulerCommand::PendingCount { reply })
+            .await
+    }
+
+    /// Execution time of the earliest pending event, or `None` if nothing is scheduled.
+    pub async fn next_execution_time(&self) -> ToolboxResult<Option<T::Time>> {
+        self.request(|reply| SchedulerCommand::NextExecutionTime { reply })
+            .await
+    }
+
+    /// Every pending event, earliest first. Recurring events are listed once, at their next
+    /// execution time.
+    pub async fn pending_events(&self) -> ToolboxResult<Vec<PendingEvent<T>>> {
+        self.pending_within(..).await
+    }
+
+    /// Pending events executing within `range`, earliest first.
+    ///
+    /// # Arguments
+    /// * `range` - The execution times to include, for example `start..end`.
+    pub async fn pending_within(
+        &self,
+        range: impl RangeBounds<T::Time>,
+    ) -> ToolboxResult<Vec<PendingEvent<T>>> {
+        let range = (range.start_bound().cloned(), range.end_bound().cloned());
+        self.request(|reply| SchedulerCommand::Pending { range, reply })
+            .await
+    }
+
+    /// Stop the scheduler, dropping every pending event. Resolves once the actor task has exited.
+    ///
+    /// # Errors
+    /// [`ToolboxError::ActorClosed`] if the actor task panicked or was cancelled.
+    pub async fn stop(&self) -> ToolboxResult {
+        // The actor may already have exited, in which case there is nothing to stop.
+        _ = self.command_sender.send(SchedulerCommand::Stop);
+        match self.join().await {
+            ExitReason::Aborted(message) => Err(ToolboxError::ActorClosed(message)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Stop the scheduler gracefully according to `mode` and wait for the actor task to exit.
+    ///
+    /// # Returns
+    /// The events that never fired, earliest first, and the reason the actor exited.
+    ///
+    /// # Errors
+    /// [`ToolboxError::ActorClosed`] if the scheduler had already stopped.
+    /// [`ToolboxError::ShutdownPending`] if an earlier [`ShutdownMode::FireUntil`] shutdown is
+    /// still waiting for its deadline. Only the earlier caller receives the report.
+    pub async fn shutdown(&self, mode: ShutdownMode<T::Time>) -> ToolboxResult<ShutdownReport<E>> {
+        let pending = self
+            .request(|reply| SchedulerCommand::Shutdown { mode, reply })
+            .await??;
+        Ok(ShutdownReport {
+            pending,
+            reason: self.join().await,
         })
     }
 
-    pub async fn stop(&self) -> Result<(), mpsc::error::SendError<SchedulerCommand<E>>> {
-        self.command_sender.send(SchedulerCommand::Stop)
+    /// Wait for the actor task to exit, returning why it did. Every call after the first returns
+    /// the same reason immediately.
+    pub async fn join(&self) -> ExitReason {
+        let mut exit = self.exit.clone();
+        let reason = exit
+            .wait_for(Option::is_some)
+            .await
+            .map(|reason| reason.clone());
+        match reason {
+            Ok(reason) => reason.expect("exit reason is set"),
+            Err(_) => ExitReason::Aborted("runtime shut down".to_string()),
+        }
+    }
+
+    /// Why the actor task exited, or `None` while it is still running.
+    pub fn exit_reason(&self) -> Option<ExitReason> {
+        self.exit.borrow().clone()
+    }
+}
+
+impl<T, E, P> SchedulerHandle<T, E, P>
+where
+    T: DiscreteClock + Sync + Send + 'static,
+    E: PayloadEvent<T, P> + 'static,
+    P: Clone + Send + Sync + 'static,
+{
+    /// Create a handle to a scheduler that runs as fast as possible, jumping the clock directly
+    /// to the next event instead of waiting for it. The `event_sender` is dropped once the stop
+    /// time is reached.
+    pub fn new_discrete(
+        clock: Arc<RwLock<T>>,
+        event_sender: impl Into<NotificationSender<T, P>>,
+        options: DiscreteEventOptions<T::Time>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let clock_clone = Arc::clone(&clock);
+        let mut actor = Scheduler::new(receiver, event_sender, clock_clone)
+            .with_handler_concurrency(options.handler_concurrency);
+
+        let task = tokio::spawn(async move {
+            actor
+                .run_discrete(options)
+                .await
+                .unwrap_or_else(|err| ExitReason::Failed(err.to_string()))
+        });
+
+        Self::from_parts(sender, clock, task, 0)
+    }
+}
+
+impl<T, E, P> SchedulerHandle<T, E, P>
+where
+    T: Clock + Sync + Send + 'static,
+    T::Time: TickTime,
+    E: PayloadEvent<T, P> + 'static,
+    P: Clone + Send + Sync + 'static,
+{
+    /// Keep the pending events in `backend`, moving any already queued. Events keep the order
+    /// they fire in. The heap is used unless another backend is chosen.
+    pub fn with_queue(self, backend: QueueBackend) -> Self {
+        // Applied before any command sent after it. Nothing is lost if the actor has exited.
+        _ = self.command_sender.send(SchedulerCommand::SetQueue {
+            backend,
+            tick: TickTime::tick,
+        });
+        self
+    }
+}
+
+impl<T, E, P> SchedulerHandle<T, E, P>
+where
+    T: Clock + Sync + Send + 'static,
+    E: PayloadEvent<T, P> + Serialize + DeserializeOwned + 'static,
+    P: Clone + Send + Sync + 'static,
+{
+    /// Create a handle to a scheduler that records every change to its queue in `journal`. The
+    /// scheduler starts with the events recovered from the journal, handling those whose time
+    /// has already passed according to the journal's
+    /// [`RecoveryPolicy`](super::persistence::RecoveryPolicy). New events are given IDs that
+    /// follow the recovered ones.
+    pub fn with_journal(
+        clock: Arc<RwLock<T>>,
+        event_sender: impl Into<NotificationSender<T, P>>,
+        options: SchedulerOptions,
+        journal: Journal<E>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let clock_clone = Arc::clone(&clock);
+        let next_id = journal.next_id();
+        let mut actor = Scheduler::new(receiver, event_sender, clock_clone)
+            .with_options(options)
+            .with_journal(journal);
+
+        let task = tokio::spawn(async move {
+            actor
+                .run()
+                .await
+                .unwrap_or_else(|err| ExitReason::Failed(err.to_string()))
+        });
+
+        Self::from_parts(sender, clock, task, next_id)
+    }
+}
+
+impl<T, E, P> SchedulerHandle<T, E, P>
+where
+    T: DiscreteClock + Sync + Send + 'static,
+    E: PayloadEvent<T, P> + Serialize + DeserializeOwned + 'static,
+    P: Clone + Send + Sync + 'static,
+{
+    /// Create a handle to a discrete-event scheduler, as with
+    /// [`new_discrete`](Self::new_discrete), that records every change to its queue in
+    /// `journal` and starts with the events recovered from it, as with
+    /// [`with_journal`](Self::with_journal). Recovered events that are fired despite their time
+    /// having passed fire at the clock's current time, which never moves backwards.
+    pub fn new_discrete_with_journal(
+        clock: Arc<RwLock<T>>,
+        event_sender: impl Into<NotificationSender<T, P>>,
+        options: DiscreteEventOptions<T::Time>,
+        journal: Journal<E>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let clock_clone = Arc::clone(&clock);
+        let next_id = journal.next_id();
+        let mut actor = Scheduler::new(receiver, event_sender, clock_clone)
+            .with_handler_concurrency(options.handler_concurrency)
+            .with_journal(journal);
+
+        let task = tokio::spawn(async move {
+            actor
+                .run_discrete(options)
+                .await
+                .unwrap_or_else(|err| ExitReason::Failed(err.to_string()))
+        });
+
+        Self::from_parts(sender, clock, task, next_id)
     }
 }
 
-pub struct Scheduler<T: Clock, E: Event<T>> {
+pub struct Scheduler<T: Clock, E: Event<T>, P = ()> {
     clock: Arc<RwLock<T>>,
-    command_receiver: mpsc::UnboundedReceiver<SchedulerCommand<E>>,
-    event_sender: mpsc::UnboundedSender<EventNotification<T>>,
+    command_receiver: mpsc::UnboundedReceiver<SchedulerCommand<T, E, P>>,
+    event_sender: NotificationSender<T, P>,
+    options: SchedulerOptions,
+    /// Discrete mode only. Events executing before this time are rejected because the clock has
+    /// already passed it. Real time schedulers fire late events immediately instead.
+    earliest_time: Option<T::Time>,
+    handlers: HandlerRegistry<T, P>,
+    subscriptions: Subscriptions<T, P>,
+    /// Shutdown requested with [`SchedulerCommand::Shutdown`], answered when the actor exits.
+    shutdown: Option<PendingShutdown<T, E>>,
+    journal: Option<Box<dyn QueueJournal<E>>>,
+    /// Events recovered from the journal, queued when the scheduler starts running.
+    recovered: Vec<UnfiredEvent<E>>,
+    /// Error that stopped the scheduler while applying a command.
+    failure: Option<ToolboxError>,
 }
 
-impl<T: Clock, E: Event<T>> Scheduler<T, E> {
+struct PendingShutdown<T: Clock, E> {
+    mode: ShutdownMode<T::Time>,
+    reply: oneshot::Sender<ToolboxResult<Vec<UnfiredEvent<E>>>>,
+}
+
+impl<T, E, P> Scheduler<T, E, P>
+where
+    T: Clock + 'static,
+    E: PayloadEvent<T, P>,
+    P: Clone + Send + Sync + 'static,
+{
     pub fn new(
-        command_receiver: mpsc::UnboundedReceiver<SchedulerCommand<E>>,
-        event_sender: mpsc::UnboundedSender<EventNotification<T>>,
+        command_receiver: mpsc::UnboundedReceiver<SchedulerCommand<T, E, P>>,
+        event_sender: impl Into<NotificationSender<T, P>>,
         clock: Arc<RwLock<T>>,
     ) -> Self {
         Self {
             clock,
             command_receiver,
-            event_sender,
+            event_sender: event_sender.into(),
+            options: SchedulerOptions::default(),
+            earliest_time: None,
+            handlers: HandlerRegistry::new(None),
+            subscriptions: Subscriptions::new(),
+            shutdown: None,
+            journal: None,
+            recovered: Vec::new(),
+            failure: None,
         }
     }
 
-    pub async fn run(&mut self) -> Result<(), ToolboxError> {
-        let mut events = BinaryHeap::new();
+    pub fn with_options(mut self, options: SchedulerOptions) -> Self {
+        self.handlers = HandlerRegistry::new(options.handler_concurrency);
+        self.options = options;
+        self
+    }
+
+    fn with_journal(mut self, mut journal: Journal<E>) -> Self
+    where
+        E: Serialize + DeserializeOwned + 'static,
+    {
+        self.recovered = journal.take_recovered();
+        self.journal = Some(Box::new(journal));
+        self
+    }
+
+    fn with_handler_concurrency(mut self, concurrency: Option<NonZeroUsize>) -> Self {
+        self.handlers = HandlerRegistry::new(concurrency);
+        self
+    }
+
+    pub async fn run(&mut self) -> Result<ExitReason, ToolboxError> {
+        let mut events = EventQueue::new();
         let notify = Notify::new();
         let mut sleep_time: Option<tokio::time::Duration> = None;
+        let (mut clock_changes, fires_missed, mut last_now) = {
+            let clock = self.clock.read().await;
+            (
+                clock.changes(),
+                clock.fires_missed_occurrences(),
+                clock.now(),
+            )
+        };
+        let mut seeks = seek_count(&clock_changes);
+        self.restore(&mut events, last_now)?;
+        // Fire recovered events that are already due.
+        notify.notify_one();
 
         loop {
             tokio::select! {
                 Some(task) = self.command_receiver.recv() => {
-                    match task {
-                        SchedulerCommand::Schedule(evt) => {
-                            events.push(Reverse(evt));
-                            notify.notify_one();
-                        }
-                        SchedulerCommand::Cancel { name } => {
-                            events.retain(|Reverse(evt)| evt.name() != name);
-                        }
-                        SchedulerCommand::Stop => {
-                            events.clear();
-                            self.command_receiver.close();
-                            break;
-                        }
+                    if !self.apply_command(&mut events, task) {
+                        break;
                     }
+                    notify.notify_one();
                 },
                 _ = notify.notified() => {
-                    let now = self.clock.read().await.now();
-                    while let Some(task) = events.peek() {
-                        if task.0.execution_time() <= now {
-                            if let Some(Reverse(task)) = events.pop() {
-                                let next_time = task.next_time();
-                                if next_time.execution_time() > now {
-                                    events.push(Reverse(next_time));
+                    // Apply queued commands first so events scheduled before the clock moved
+                    // are fired in order.
+                    if !self.apply_pending_commands(&mut events) || self.shutdown_due(&events) {
+                        break;
+                    }
+                    // Sleep until the clock announces a change while it is halted.
+                    let (halted, now) = {
+                        let clock = self.clock.read().await;
+                        (clock.is_halted(), clock.now())
+                    };
+                    if halted {
+                        sleep_time = None;
+                        continue;
+                    }
+                    let mut catch_up = fires_missed;
+                    if seeks != seek_count(&clock_changes) {
+                        seeks = seek_count(&clock_changes);
+                        if now < last_now {
+                            rearm_events(&mut events, now);
+                            self.compact_journal(&events, true)?;
+                        } else if now > last_now {
+                            match self.options.seek_policy {
+                                SeekPolicy::Fire => catch_up = true,
+                                SeekPolicy::Skip => {
+                                    skip_events_before(&mut events, now);
+                                    self.compact_journal(&events, true)?;
                                 }
-                                self.event_sender.send(EventNotification {
-                                    name: task.name().to_string(),
-                                    time: task.execution_time(),
-                                })?;
                             }
-                        } else {
-                            let time_diff = self.clock.read().await.delay_time(task.0.execution_time());
-                            let duration = tokio::time::Duration::from_millis(i64::from(time_diff) as u64);
-                            sleep_time = Some(duration);
-                            break;
                         }
                     }
-
-                    if events.is_empty() {
-                        sleep_time = None;
+                    last_now = now;
+                    // The clock is not locked while firing, as sending may wait for consumers.
+                    if !self.fire_due_events(&mut events, now, catch_up, None).await?
+                        || self.shutdown_due(&events)
+                    {
+                        break;
                     }
+                    let clock = self.clock.read().await;
+                    sleep_time = events.next_time().map(|next| {
+                        let time_diff = clock.delay_time(next);
+                        tokio::time::Duration::from_millis(i64::from(time_diff) as u64)
+                    });
                 },
                 _ = async {
                     if let Some(duration) = sleep_time {
@@ -137,8 +868,2018 @@ impl<T: Clock, E: Event<T>> Scheduler<T, E> {
                 } => {
                     notify.notify_one();
                 }
+                Some(()) = clock_changed(&mut clock_changes) => {
+                    notify.notify_one();
+                }
+            }
+        }
+        self.finish(&mut events, ExitReason::Stopped).await
+    }
+
+    /// Apply a command to the queue of pending events. Returns `false` once the scheduler should
+    /// stop running.
+    fn apply_command(
+        &mut self,
+        events: &mut EventQueue<T, E>,
+        command: SchedulerCommand<T, E, P>,
+    ) -> bool {
+        match command {
+            SchedulerCommand::Schedule { id, event, reply } => {
+                if let Err(err) = self.accepts(&event) {
+                    _ = reply.send(Err(err));
+                } else {
+                    if !self.record(Record::Schedule {
+                        id,
+                        occurrence: 0,
+                        event: &event,
+                    }) {
+                        return false;
+                    }
+                    events.push(id, event);
+                    if !self.acknowledge(move || _ = reply.send(Ok(()))) {
+                        return false;
+                    }
+                }
+            }
+            SchedulerCommand::Cancel { id, reply } => {
+                let cancelled = events.cancel(id);
+                if cancelled && !self.record(Record::Cancel { id }) {
+                    return false;
+                }
+                if !self.acknowledge(move || _ = reply.send(usize::from(cancelled))) {
+                    return false;
+                }
+            }
+            SchedulerCommand::CancelByName { name, reply } => {
+                let cancelled = events.cancel_by_name(&name);
+                for &id in &cancelled {
+                    if !self.record(Record::Cancel { id }) {
+                        return false;
+                    }
+                }
+                if !self.acknowledge(move || _ = reply.send(cancelled.len())) {
+                    return false;
+                }
+            }
+            SchedulerCommand::Replace { id, event, reply } => {
+                let Some(modification) = self.modify(events, id, Some(event)) else {
+                    return false;
+                };
+                if !self.acknowledge(move || _ = reply.send(modification)) {
+                    return false;
+                }
+            }
+            SchedulerCommand::Reschedule { id, time, reply } => {
+                let Some(event) = events.event(id) else {
+                    _ = reply.send(Ok(Modification::AlreadyFired));
+                    return true;
+                };
+                let moved = event.with_execution_time(time(event.execution_time()));
+                let Some(modification) = self.modify(events, id, moved) else {
+                    return false;
+                };
+                if !self.acknowledge(move || _ = reply.send(modification)) {
+                    return false;
+                }
+            }
+            SchedulerCommand::ScheduledTime { id, reply } => {
+                _ = reply.send(events.time_of(id));
+            }
+            SchedulerCommand::AddHandler {
+                target,
+                handler,
+                reply,
+            } => {
+                _ = reply.send(self.handlers.add(target, handler));
+            }
+            SchedulerCommand::RemoveHandler { id, reply } => {
+                _ = reply.send(self.handlers.remove(id));
+            }
+            SchedulerCommand::HandlerFailures { reply } => {
+                _ = reply.send(self.handlers.subscribe_failures());
+            }
+            SchedulerCommand::Subscribe {
+                filter,
+                sender,
+                drain,
+                reply,
+            } => {
+                self.subscriptions.add(filter, sender, drain);
+                _ = reply.send(());
+            }
+            SchedulerCommand::SubscriberCount { reply } => {
+                _ = reply.send(self.subscriptions.len());
+            }
+            SchedulerCommand::PendingCount { reply } => {
+                _ = reply.send(events.len());
+            }
+            SchedulerCommand::NextExecutionTime { reply } => {
+                _ = reply.send(events.next_time());
+            }
+            SchedulerCommand::Pending { range, reply } => {
+                _ = reply.send(events.pending(&range));
+            }
+            SchedulerCommand::Shutdown { mode, reply } => {
+                if self.shutdown.is_some() {
+                    _ = reply.send(Err(ToolboxError::ShutdownPending));
+                    return true;
+                }
+                self.shutdown = Some(PendingShutdown { mode, reply });
+                if !matches!(mode, ShutdownMode::FireUntil(_)) {
+                    self.command_receiver.close();
+                    return false;
+                }
+            }
+            SchedulerCommand::SetQueue { backend, tick } => {
+                events.set_backend(backend, tick);
             }
+            SchedulerCommand::Stop => {
+                self.command_receiver.close();
+                return false;
+            }
+        }
+        if let Err(err) = self.compact_journal(events, false) {
+            self.failure = Some(err);
+            return false;
+        }
+        true
+    }
+
+    /// Returns an error if `event` executes before the earliest time the scheduler can fire it.
+    fn accepts(&self, event: &E) -> ToolboxResult {
+        if self
+            .earliest_time
+            .is_some_and(|earliest| event.execution_time() < earliest)
+        {
+            return Err(ToolboxError::EventRejected(format!(
+                "{} executes before the current simulation time",
+                event.name()
+            )));
         }
         Ok(())
     }
+
+    /// Replace the pending event `id` with `event`, keeping its occurrence count and sequence
+    /// number. A `None` event could not be moved and is reported as
+    /// [`Modification::Unsupported`]. Returns `None` if the journal could not record the change,
+    /// in which case the scheduler stops.
+    fn modify(
+        &mut self,
+        events: &mut EventQueue<T, E>,
+        id: EventId,
+        event: Option<E>,
+    ) -> Option<ToolboxResult<Modification<T::Time>>> {
+        if events.time_of(id).is_none() {
+            return Some(Ok(Modification::AlreadyFired));
+        }
+        let Some(event) = event else {
+            return Some(Ok(Modification::Unsupported));
+        };
+        if let Err(err) = self.accepts(&event) {
+            return Some(Err(err));
+        }
+        if !self.record(Record::Replace { id, event: &event }) {
+            return None;
+        }
+        let time = event.execution_time();
+        events.replace(id, event);
+        Some(Ok(Modification::Applied(time)))
+    }
+
+    /// Append `record` to the journal, if there is one. Returns `false` if the write failed, in
+    /// which case the scheduler stops with the error.
+    fn record(&mut self, record: Record<&E>) -> bool {
+        let Some(journal) = self.journal.as_mut() else {
+            return true;
+        };
+        match journal.append(record) {
+            Ok(()) => true,
+            Err(err) => {
+                self.failure = Some(err);
+                false
+            }
+        }
+    }
+
+    /// Call `reply` once the journal has written and synced every record so far, or straight
+    /// away without a journal. Returns `false` if the journal failed, in which case the
+    /// scheduler stops with the error and `reply` is dropped.
+    fn acknowledge(&mut self, reply: impl FnOnce() + Send + 'static) -> bool {
+        let Some(journal) = self.journal.as_mut() else {
+            reply();
+            return true;
+        };
+        match journal.after_writes(Box::new(move |written| {
+            if written.is_ok() {
+                reply();
+            }
+        })) {
+            Ok(()) => true,
+            Err(err) => {
+                self.failure = Some(err);
+                false
+            }
+        }
+    }
+
+    /// Wait for the journal, if there is one, to write and sync every record so far.
+    async fn flush_journal(&mut self) -> ToolboxResult {
+        let Some(journal) = self.journal.as_mut() else {
+            return Ok(());
+        };
+        let (done, written) = oneshot::channel();
+        journal.after_writes(Box::new(move |result| _ = done.send(result)))?;
+        written.await?
+    }
+
+    /// Rewrite the journal as a snapshot of `events` once it has grown enough, or straight away
+    /// if `force` is set.
+    fn compact_journal(&mut self, events: &EventQueue<T, E>, force: bool) -> ToolboxResult {
+        match self.journal.as_mut() {
+            Some(journal) if force || journal.needs_compaction() => journal.compact(
+                events
+                    .by_sequence()
+                    .into_iter()
+                    .map(|scheduled| (scheduled.id, scheduled.occurrence, &scheduled.event))
+                    .collect(),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Queue the events recovered from the journal, applying its [`RecoveryPolicy`] to those
+    /// due before `now`.
+    fn restore(&mut self, events: &mut EventQueue<T, E>, now: T::Time) -> ToolboxResult {
+        let Some(recovery) = self.journal.as_ref().map(|journal| journal.recovery()) else {
+            return Ok(());
+        };
+        for unfired in std::mem::take(&mut self.recovered) {
+            events.push_occurrence(unfired.id, unfired.event, unfired.occurrence);
+        }
+        match recovery {
+            RecoveryPolicy::Fire => {}
+            RecoveryPolicy::Skip => skip_events_before(events, now),
+            RecoveryPolicy::Discard => while events.pop_before(now).is_some() {},
+        }
+        self.compact_journal(events, true)
+    }
+
+    /// Apply every command already queued without waiting for more. Returns `false` once the
+    /// scheduler should stop running.
+    fn apply_pending_commands(&mut self, events: &mut EventQueue<T, E>) -> bool {
+        while let Ok(task) = self.command_receiver.try_recv() {
+            if !self.apply_command(events, task) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Apply `commands` in order. Returns `false` once the scheduler should stop running.
+    fn apply_commands(
+        &mut self,
+        events: &mut EventQueue<T, E>,
+        commands: Vec<SchedulerCommand<T, E, P>>,
+    ) -> bool {
+        commands
+            .into_iter()
+            .all(|command| self.apply_command(events, command))
+    }
+
+    /// Returns `true` once a [`ShutdownMode::FireUntil`] shutdown has no more events to fire.
+    fn shutdown_due(&self, events: &EventQueue<T, E>) -> bool {
+        match self.shutdown.as_ref().map(|shutdown| shutdown.mode) {
+            Some(ShutdownMode::FireUntil(deadline)) => {
+                events.next_time().is_none_or(|next| next > deadline)
+            }
+            _ => false,
+        }
+    }
+
+    /// Complete a requested shutdown, flushing events if asked to and replying with the events
+    /// that never fired. Pending events are dropped if no shutdown was requested.
+    ///
+    /// # Returns
+    /// [`ExitReason::Shutdown`] if a shutdown was requested, otherwise `reason`.
+    async fn finish(
+        &mut self,
+        events: &mut EventQueue<T, E>,
+        reason: ExitReason,
+    ) -> Result<ExitReason, ToolboxError> {
+        self.command_receiver.close();
+        if let Some(err) = self.failure.take() {
+            return Err(err);
+        }
+        let Some(shutdown) = self.shutdown.take() else {
+            // The journal keeps the dropped events so that they are recovered on restart.
+            self.compact_journal(events, true)?;
+            self.flush_journal().await?;
+            events.clear();
+            return Ok(reason);
+        };
+        if let ShutdownMode::FlushUntil(deadline) = shutdown.mode {
+            self.fire_due_events(events, deadline, true, None).await?;
+        }
+        self.compact_journal(events, true)?;
+        self.flush_journal().await?;
+        let unfired = events
+            .drain()
+            .into_iter()
+            .map(|scheduled| UnfiredEvent {
+                id: scheduled.id,
+                event: scheduled.event,
+                occurrence: scheduled.occurrence,
+            })
+            .collect();
+        _ = shutdown.reply.send(Ok(unfired));
+        Ok(ExitReason::Shutdown)
+    }
+
+    /// Send a notification for every event due at or before `now`, re-queuing recurring events.
+    /// Missed occurrences are handled by each event's [`MisfirePolicy`], defaulting to
+    /// [`MisfirePolicy::FireAll`] when `fires_missed` is set and [`MisfirePolicy::Skip`]
+    /// otherwise.
+    ///
+    /// Each notification is sent to the scheduler's own sender first and only then to handlers
+    /// and subscribers. Commands received while waiting for a full channel are applied once the
+    /// notification is delivered, except a stop or shutdown. One received while waiting for the
+    /// scheduler's sender abandons the notification and leaves the event pending as it was
+    /// popped. One received while waiting for a subscriber abandons the remaining subscribers,
+    /// and the occurrence counts as fired.
+    ///
+    /// # Returns
+    /// `false` if a command received while firing stopped the scheduler.
+    async fn fire_due_events(
+        &mut self,
+        events: &mut EventQueue<T, E>,
+        now: T::Time,
+        fires_missed: bool,
+        drain: Option<&DrainTracker>,
+    ) -> Result<bool, ToolboxError> {
+        while let Some(scheduled) = events.pop_due(now) {
+            let ScheduledEvent {
+                id,
+                event,
+                name,
+                occurrence: popped_occurrence,
+                sequence,
+            } = scheduled;
+            let policy = event.misfire_policy().unwrap_or(if fires_missed {
+                MisfirePolicy::FireAll
+            } else {
+                MisfirePolicy::Skip
+            });
+
+            let mut occurrence = popped_occurrence;
+            let mut missed = 0;
+            let mut coalesced = None;
+            if policy == MisfirePolicy::Coalesce {
+                while let Some(next) = next_occurrence(coalesced.as_ref().unwrap_or(&event))
+                    .filter(|next| next.execution_time() <= now)
+                {
+                    coalesced = Some(next);
+                    occurrence += 1;
+                    missed += 1;
+                }
+            }
+            let task = coalesced.as_ref().unwrap_or(&event);
+            let next = match policy {
+                MisfirePolicy::FireAll => next_occurrence(task).map(|next| (next, occurrence + 1)),
+                MisfirePolicy::Delay => match task.with_execution_time(now) {
+                    Some(delayed) => next_occurrence(&delayed).map(|next| (next, occurrence + 1)),
+                    None => first_occurrence_after(task, occurrence, now),
+                },
+                MisfirePolicy::Coalesce | MisfirePolicy::Skip => {
+                    first_occurrence_after(task, occurrence, now)
+                }
+            };
+
+            // Coalescing may have moved to an occurrence with a different name.
+            let fired_name = shared_name(Arc::clone(&name), task);
+            let notification = EventNotification {
+                payload: task.payload(),
+                id,
+                name: Arc::clone(&fired_name),
+                time: task.execution_time(),
+                occurrence,
+                missed,
+                is_final: next.is_none(),
+                tags: task.tags().to_vec(),
+            };
+            let mut deferred = Vec::new();
+            let Some(sent) = until_stopped(
+                self.event_sender.send(notification.clone()),
+                &mut self.command_receiver,
+                self.shutdown.is_some(),
+                &mut deferred,
+            )
+            .await
+            else {
+                events.push_scheduled(ScheduledEvent {
+                    id,
+                    event,
+                    name,
+                    occurrence: popped_occurrence,
+                    sequence,
+                });
+                return Ok(self.apply_commands(events, deferred));
+            };
+            if sent? {
+                if let Some(drain) = drain {
+                    drain.sent();
+                }
+            }
+            self.handlers.dispatch(&notification, drain);
+            until_stopped(
+                self.subscriptions.publish(&notification, drain),
+                &mut self.command_receiver,
+                self.shutdown.is_some(),
+                &mut deferred,
+            )
+            .await;
+
+            // Journal the firing once it has been delivered, so that a crash in between fires
+            // the occurrence again on recovery rather than losing it.
+            if let Some(journal) = self.journal.as_mut() {
+                journal.append(Record::Fire {
+                    id,
+                    next: next.as_ref().map(|(event, occurrence)| NextOccurrence {
+                        occurrence: *occurrence,
+                        event,
+                    }),
+                })?;
+            }
+            if let Some((next, occurrence)) = next {
+                events.push_scheduled(ScheduledEvent {
+                    id,
+                    name: shared_name(fired_name, &next),
+                    event: next,
+                    occurrence,
+                    sequence,
+                });
+            }
+            if !self.apply_commands(events, deferred) {
+                return Ok(false);
+            }
+        }
+        self.compact_journal(events, false)?;
+        Ok(true)
+    }
+}
+
+impl<T, E, P> Scheduler<T, E, P>
+where
+    T: DiscreteClock + 'static,
+    E: PayloadEvent<T, P>,
+    P: Clone + Send + Sync + 'static,
+{
+    /// Run as a discrete-event simulation. Instead of sleeping until the next event is due, the
+    /// clock is jumped directly to the execution time of the earliest event and the event fired.
+    /// Nothing is fired while the clock is halted, so pause the clock while scheduling
+    /// the initial events and resume it to begin the run. The run completes once the queue is
+    /// empty or the next event would execute after the stop time.
+    pub async fn run_discrete(
+        &mut self,
+        options: DiscreteEventOptions<T::Time>,
+    ) -> Result<ExitReason, ToolboxError> {
+        let mut events = EventQueue::new();
+        let (mut clock_changes, now) = {
+            let clock = self.clock.read().await;
+            (clock.changes(), clock.now())
+        };
+        self.earliest_time = Some(now);
+        self.restore(&mut events, now)?;
+
+        loop {
+            if !self.apply_pending_commands(&mut events) || self.shutdown_due(&events) {
+                return self.finish(&mut events, ExitReason::Stopped).await;
+            }
+
+            if let Some(drain) = options
+                .drain
+                .as_ref()
+                .filter(|drain| drain.outstanding() > 0)
+            {
+                tokio::select! {
+                    _ = drain.drained() => {},
+                    Some(task) = self.command_receiver.recv() => {
+                        if !self.apply_command(&mut events, task) {
+                            return self.finish(&mut events, ExitReason::Stopped).await;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            // The clock is checked and jumped under one lock, as a halted clock ignores jumps.
+            let mut clock = self.clock.write().await;
+            if clock.is_halted() {
+                drop(clock);
+                tokio::select! {
+                    Some(()) = clock_changed(&mut clock_changes) => {},
+                    Some(task) = self.command_receiver.recv() => {
+                        if !self.apply_command(&mut events, task) {
+                            return self.finish(&mut events, ExitReason::Stopped).await;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let Some(next) = events.next_time() else {
+                drop(clock);
+                return self.finish(&mut events, ExitReason::Completed).await;
+            };
+
+            if let Some(stop_time) = options.stop_time.filter(|stop_time| next > *stop_time) {
+                clock.jump_to(stop_time);
+                drop(clock);
+                return self.finish(&mut events, ExitReason::Completed).await;
+            }
+            // Recovered events that passed while the scheduler was down fire at once.
+            let next = self
+                .earliest_time
+                .map_or(next, |earliest| next.max(earliest));
+
+            clock.jump_to(next);
+            drop(clock);
+            self.earliest_time = Some(next);
+            if let Some(changes) = clock_changes.as_mut() {
+                changes.borrow_and_update();
+            }
+            if !self
+                .fire_due_events(&mut events, next, true, options.drain.as_ref())
+                .await?
+            {
+                return self.finish(&mut events, ExitReason::Stopped).await;
+            }
+        }
+    }
+}
+
+/// Wait for `delivery` to complete, collecting the commands received meanwhile in `deferred`.
+/// A stop, or a shutdown while none is pending, abandons the delivery.
+///
+/// # Returns
+/// The output of `delivery`, or `None` if it was abandoned.
+async fn until_stopped<T: Clock, E: Event<T>, P, F: Future>(
+    delivery: F,
+    commands: &mut mpsc::UnboundedReceiver<SchedulerCommand<T, E, P>>,
+    shutdown_pending: bool,
+    deferred: &mut Vec<SchedulerCommand<T, E, P>>,
+) -> Option<F::Output> {
+    tokio::pin!(delivery);
+    loop {
+        tokio::select! {
+            biased;
+            output = &mut delivery => return Some(output),
+            Some(command) = commands.recv() => {
+                let stops = match &command {
+                    SchedulerCommand::Stop => true,
+                    SchedulerCommand::Shutdown { mode, .. } => {
+                        !shutdown_pending
+                            && !deferred
+                                .iter()
+                                .any(|command| matches!(command, SchedulerCommand::Shutdown { .. }))
+                            && !matches!(mode, ShutdownMode::FireUntil(_))
+                    }
+                    _ => false,
+                };
+                deferred.push(command);
+                if stops {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// `name` if it is still the name of `event`, otherwise a copy of the name of `event`.
+fn shared_name<T: Clock, E: Event<T>>(name: Arc<str>, event: &E) -> Arc<str> {
+    if *name == *event.name() {
+        name
+    } else {
+        event.name().into()
+    }
+}
+
+/// Number of seeks announced by the clock so far.
+fn seek_count(changes: &Option<ClockChanges>) -> u64 {
+    changes
+        .as_ref()
+        .map(|changes| *changes.borrow())
+        .unwrap_or_default()
+}
+
+/// Drop events due before `now`. Recurring events are moved to their first occurrence at or
+/// after `now`.
+fn skip_events_before<T: Clock, E: Event<T>>(events: &mut EventQueue<T, E>, now: T::Time) {
+    let mut skipped = Vec::new();
+    while let Some(scheduled) = events.pop_before(now) {
+        skipped.push(scheduled);
+    }
+    for mut scheduled in skipped {
+        while let Some(next) = next_occurrence(&scheduled.event) {
+            scheduled.event = next;
+            scheduled.occurrence += 1;
+            if scheduled.event.execution_time() >= now {
+                events.push_scheduled(scheduled);
+                break;
+            }
+        }
+    }
+}
+
+/// Move recurring events back to their earliest occurrence at or after `now` after the clock
+/// moved backwards.
+fn rearm_events<T: Clock, E: Event<T>>(events: &mut EventQueue<T, E>, now: T::Time) {
+    events.remap(|mut scheduled| {
+        while let Some(previous) = scheduled.event.previous_time() {
+            if previous.execution_time() < now
+                || previous.execution_time() >= scheduled.event.execution_time()
+            {
+                break;
+            }
+            scheduled.event = previous;
+            scheduled.occurrence = scheduled.occurrence.saturating_sub(1);
+        }
+        scheduled
+    });
+}
+
+/// The first occurrence of `event` after `now` together with its occurrence index, skipping
+/// every occurrence at or before `now`.
+fn first_occurrence_after<T: Clock, E: Event<T>>(
+    event: &E,
+    mut occurrence: u64,
+    now: T::Time,
+) -> Option<(E, u64)> {
+    let mut next = next_occurrence(event)?;
+    occurrence += 1;
+    while next.execution_time() <= now {
+        next = next_occurrence(&next)?;
+        occurrence += 1;
+    }
+    Some((next, occurrence))
+}
+
+/// The occurrence after `event`, with its remaining count updated through [`Event::count`].
+/// Returns `None` once the count is exhausted or the event does not recur.
+fn next_occurrence<T: Clock, E: Event<T>>(event: &E) -> Option<E> {
+    let next = match event.remaining() {
+        Some(remaining) if remaining <= 1 => return None,
+        Some(remaining) => event.next_time().count(remaining - 1),
+        None => event.next_time(),
+    };
+    (next.execution_time() > event.execution_time()).then_some(next)
+}
+
+/// Resolves when the clock announces a change. Never resolves for clocks without notifications.
+async fn clock_changed(changes: &mut Option<ClockChanges>) -> Option<()> {
+    match changes.as_mut() {
+        Some(changes) => changes.changed().await.ok(),
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod scheduler_tests {
+    use std::{cmp::Ordering, sync::Arc};
+
+    use tokio::sync::{mpsc, RwLock};
+
+    use crate::error::ToolboxError;
+    use crate::scheduler::event::{Event, EventNotification, PayloadEvent};
+    use crate::scheduler::handler::HandlerTarget;
+    use crate::scheduler::internal::{
+        DiscreteEventOptions, DrainTracker, Modification, QueueBackend, SchedulerHandle,
+        SchedulerOptions, SeekPolicy,
+    };
+    use crate::scheduler::shutdown::{ExitReason, ShutdownMode, ShutdownReport};
+    use crate::scheduler::standard_events::OneShotEvent;
+    use crate::scheduler::subscription::NotificationFilter;
+    use crate::time::{
+        Clock, DiscreteClock, ManualClock, SimClock, SimDuration, SimTime, StepSimClock,
+        TimeDuration, WallTime,
+    };
+
+    #[derive(Debug)]
+    struct TestEvent {
+        name: String,
+        time: SimTime,
+        period: Option<SimDuration>,
+        remaining: Option<u64>,
+    }
+
+    impl TestEvent {
+        fn once(name: &str, time: SimTime) -> Self {
+            Self {
+                name: name.to_string(),
+                time,
+                period: None,
+                remaining: None,
+            }
+        }
+
+        fn every(name: &str, time: SimTime, period: SimDuration) -> Self {
+            Self {
+                name: name.to_string(),
+                time,
+                period: Some(period),
+                remaining: None,
+            }
+        }
+
+        fn times(self, times: u64) -> Self {
+            Self {
+                remaining: Some(times),
+                ..self
+            }
+        }
+    }
+
+    impl PartialEq for TestEvent {
+        fn eq(&self, other: &Self) -> bool {
+            self.cmp(other) == Ordering::Equal
+        }
+    }
+
+    impl Eq for TestEvent {}
+
+    impl PartialOrd for TestEvent {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for TestEvent {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.time
+                .cmp(&other.time)
+                .then_with(|| self.name.cmp(&other.name))
+        }
+    }
+
+    impl<C: Clock<Time = SimTime>> Event<C> for TestEvent {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn execution_time(&self) -> SimTime {
+            self.time
+        }
+
+        fn next_time(&self) -> Self {
+            Self {
+                name: self.name.clone(),
+                time: match self.period {
+                    Some(period) => self.time + period,
+                    None => self.time,
+                },
+                period: self.period,
+                remaining: self.remaining,
+            }
+        }
+
+        fn count(&self, new_count: u64) -> Self {
+            Self {
+                name: self.name.clone(),
+                time: self.time,
+                period: self.period,
+                remaining: Some(new_count),
+            }
+        }
+
+        fn remaining(&self) -> Option<u64> {
+            self.remaining
+        }
+
+        fn previous_time(&self) -> Option<Self> {
+            let period = self.period?.num_microseconds() as u64;
+            (self.time.as_micros() >= 2 * period).then(|| Self {
+                name: self.name.clone(),
+                time: SimTime::from_micros(self.time.as_micros() - period),
+                period: self.period,
+                remaining: self.remaining,
+            })
+        }
+    }
+
+    type Notifications<C = ManualClock> = mpsc::UnboundedReceiver<EventNotification<C>>;
+
+    fn manual_scheduler() -> (
+        Arc<RwLock<ManualClock>>,
+        SchedulerHandle<ManualClock, TestEvent>,
+        Notifications,
+    ) {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::new(Arc::clone(&clock), sender);
+        (clock, handle, receiver)
+    }
+
+    #[tokio::test]
+    async fn manual_advance_fires_due_events_in_order() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("b", SimTime::from_seconds(2)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("c", SimTime::from_seconds(10)))
+            .await
+            .unwrap();
+
+        clock.write().await.advance_by(SimDuration::seconds(5));
+
+        let first = notifications.recv().await.unwrap();
+        let second = notifications.recv().await.unwrap();
+        assert_eq!(
+            (first.name(), first.time()),
+            ("a", SimTime::from_seconds(1))
+        );
+        assert_eq!(
+            (second.name(), second.time()),
+            ("b", SimTime::from_seconds(2))
+        );
+        assert!(notifications.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn cancel_by_id_removes_only_that_event() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        let first = handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        let second = handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(2)))
+            .await
+            .unwrap();
+        assert_ne!(first, second);
+
+        assert_eq!(handle.cancel(first).await.unwrap(), 1);
+        assert_eq!(handle.cancel(first).await.unwrap(), 0);
+        clock.write().await.advance_by(SimDuration::seconds(5));
+
+        let notification = notifications.recv().await.unwrap();
+        assert_eq!(notification.id(), second);
+        assert_eq!(notification.time(), SimTime::from_seconds(2));
+        assert!(notifications.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn cancel_by_name_removes_every_matching_event() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(2)))
+            .await
+            .unwrap();
+        let kept = handle
+            .schedule(TestEvent::once("b", SimTime::from_seconds(3)))
+            .await
+            .unwrap();
+
+        assert_eq!(handle.cancel_by_name("a").await.unwrap(), 2);
+        clock.write().await.advance_by(SimDuration::seconds(5));
+
+        assert_eq!(notifications.recv().await.unwrap().id(), kept);
+        assert!(notifications.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn cancel_scheduled_event_still_cancels_by_name() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        let kept = handle
+            .schedule(TestEvent::once("b", SimTime::from_seconds(2)))
+            .await
+            .unwrap();
+
+        handle.cancel_scheduled_event("a").unwrap();
+        assert_eq!(handle.pending_count().await.unwrap(), 1);
+        clock.write().await.advance_by(SimDuration::seconds(5));
+
+        assert_eq!(notifications.recv().await.unwrap().id(), kept);
+        assert!(notifications.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn recurring_occurrences_share_id_and_replace_keeps_it() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        let id = handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(1),
+                SimDuration::seconds(1),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            handle.scheduled_time(id).await.unwrap(),
+            Some(SimTime::from_seconds(1))
+        );
+
+        clock.write().await.advance_by(SimDuration::seconds(2));
+        assert_eq!(notifications.recv().await.unwrap().id(), id);
+        assert_eq!(notifications.recv().await.unwrap().id(), id);
+        assert_eq!(
+            handle.scheduled_time(id).await.unwrap(),
+            Some(SimTime::from_seconds(3))
+        );
+
+        assert_eq!(
+            handle
+                .replace(id, TestEvent::once("tock", SimTime::from_seconds(10)))
+                .await
+                .unwrap(),
+            Modification::Applied(SimTime::from_seconds(10))
+        );
+        clock.write().await.advance_by(SimDuration::seconds(8));
+        let notification = notifications.recv().await.unwrap();
+        assert_eq!((notification.id(), notification.name()), (id, "tock"));
+        assert_eq!(handle.scheduled_time(id).await.unwrap(), None);
+        assert_eq!(
+            handle
+                .replace(id, TestEvent::once("tock", SimTime::from_seconds(20)))
+                .await
+                .unwrap(),
+            Modification::AlreadyFired
+        );
+    }
+
+    /// Event delivered to consumers as the payload of its own notifications.
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Reading {
+        time: SimTime,
+        sensor: String,
+        value: i64,
+    }
+
+    impl<C: Clock<Time = SimTime>> Event<C> for Reading {
+        fn name(&self) -> &str {
+            &self.sensor
+        }
+
+        fn execution_time(&self) -> SimTime {
+            self.time
+        }
+
+        fn next_time(&self) -> Self {
+            self.clone()
+        }
+
+        fn count(&self, _new_count: u64) -> Self {
+            self.clone()
+        }
+    }
+
+    impl<C: Clock<Time = SimTime>> PayloadEvent<C, Reading> for Reading {
+        fn payload(&self) -> Self {
+            self.clone()
+        }
+    }
+
+    /// Implements only the items required of an [`Event`] before payloads were added.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct NameOnly(SimTime);
+
+    impl<C: Clock<Time = SimTime>> Event<C> for NameOnly {
+        fn name(&self) -> &str {
+            "name-only"
+        }
+
+        fn execution_time(&self) -> SimTime {
+            self.0
+        }
+
+        fn next_time(&self) -> Self {
+            NameOnly(self.0 + SimDuration::seconds(1))
+        }
+
+        fn count(&self, _new_count: u64) -> Self {
+            NameOnly(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn name_only_events_need_no_payload() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut notifications) =
+            mpsc::unbounded_channel::<EventNotification<ManualClock>>();
+        let handle = SchedulerHandle::new(Arc::clone(&clock), sender);
+        handle
+            .schedule(NameOnly(SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        clock.write().await.advance_by(SimDuration::seconds(1));
+
+        let notification = notifications.recv().await.unwrap();
+        assert_eq!(notification.name(), "name-only");
+        assert_eq!(notification.time(), SimTime::from_seconds(1));
+        handle.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn notifications_carry_event_payload() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle: SchedulerHandle<ManualClock, Reading, Reading> =
+            SchedulerHandle::new(Arc::clone(&clock), sender);
+        let (handled_sender, mut handled) = mpsc::unbounded_channel();
+        handle
+            .add_handler(
+                HandlerTarget::Name("thermometer".to_string()),
+                move |notification: EventNotification<ManualClock, Reading>| {
+                    let handled_sender = handled_sender.clone();
+                    async move {
+                        _ = handled_sender.send(notification.payload().value);
+                        Ok(())
+                    }
+                },
+            )
+            .await
+            .unwrap();
+        let mut high = handle
+            .subscribe(NotificationFilter::predicate(
+                |notification: &EventNotification<ManualClock, Reading>| {
+                    notification.payload().value > 20
+                },
+            ))
+            .await
+            .unwrap();
+
+        for (seconds, value) in [(1, 18), (2, 25)] {
+            handle
+                .schedule(Reading {
+                    time: SimTime::from_seconds(seconds),
+                    sensor: "thermometer".to_string(),
+                    value,
+                })
+                .await
+                .unwrap();
+        }
+        clock.write().await.advance_by(SimDuration::seconds(2));
+
+        let first = notifications.recv().await.unwrap();
+        assert_eq!(first.name(), "thermometer");
+        assert_eq!(first.payload().value, 18);
+        assert_eq!(notifications.recv().await.unwrap().into_payload().value, 25);
+        assert_eq!(high.recv().await.unwrap().payload().value, 25);
+        let mut values = vec![handled.recv().await.unwrap(), handled.recv().await.unwrap()];
+        values.sort();
+        assert_eq!(values, [18, 25]);
+    }
+
+    #[tokio::test]
+    async fn reschedule_and_postpone_move_pending_events() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle: SchedulerHandle<ManualClock, OneShotEvent<ManualClock>> =
+            SchedulerHandle::new(Arc::clone(&clock), sender);
+        let a = handle
+            .schedule(OneShotEvent::new("a", SimTime::from_seconds(5)))
+            .await
+            .unwrap();
+        let b = handle
+            .schedule(OneShotEvent::new("b", SimTime::from_seconds(10)))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            handle
+                .reschedule(a, SimTime::from_seconds(12))
+                .await
+                .unwrap(),
+            Modification::Applied(SimTime::from_seconds(12))
+        );
+        assert_eq!(
+            handle.postpone(b, SimDuration::seconds(5)).await.unwrap(),
+            Modification::Applied(SimTime::from_seconds(15))
+        );
+        clock.write().await.advance_to(SimTime::from_seconds(12));
+        let notification = notifications.recv().await.unwrap();
+        assert_eq!(
+            (notification.id(), notification.time()),
+            (a, SimTime::from_seconds(12))
+        );
+
+        assert_eq!(
+            handle.postpone(a, SimDuration::seconds(1)).await.unwrap(),
+            Modification::AlreadyFired
+        );
+        assert_eq!(
+            handle.scheduled_time(b).await.unwrap(),
+            Some(SimTime::from_seconds(15))
+        );
+    }
+
+    #[tokio::test]
+    async fn reschedule_reports_events_that_cannot_move() {
+        let (_clock, handle, _notifications) = manual_scheduler();
+        let id = handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(5)))
+            .await
+            .unwrap();
+        assert_eq!(
+            handle
+                .reschedule(id, SimTime::from_seconds(6))
+                .await
+                .unwrap(),
+            Modification::Unsupported
+        );
+        assert_eq!(
+            handle.scheduled_time(id).await.unwrap(),
+            Some(SimTime::from_seconds(5))
+        );
+    }
+
+    #[tokio::test]
+    async fn pending_queries_report_queue_contents() {
+        let (clock, handle, _notifications) = manual_scheduler();
+        assert_eq!(handle.pending_count().await.unwrap(), 0);
+        assert_eq!(handle.next_execution_time().await.unwrap(), None);
+
+        let late = handle
+            .schedule(TestEvent::once("late", SimTime::from_seconds(30)))
+            .await
+            .unwrap();
+        let tick = handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(10),
+                SimDuration::seconds(10),
+            ))
+            .await
+            .unwrap();
+        clock.write().await.advance_by(SimDuration::seconds(15));
+        tokio::task::yield_now().await;
+
+        assert_eq!(handle.pending_count().await.unwrap(), 2);
+        assert_eq!(
+            handle.next_execution_time().await.unwrap(),
+            Some(SimTime::from_seconds(20))
+        );
+        let pending: Vec<_> = handle
+            .pending_events()
+            .await
+            .unwrap()
+            .iter()
+            .map(|event| (event.id(), event.name().to_string(), event.time()))
+            .collect();
+        assert_eq!(
+            pending,
+            [
+                (tick, "tick".to_string(), SimTime::from_seconds(20)),
+                (late, "late".to_string(), SimTime::from_seconds(30)),
+            ]
+        );
+
+        let window = handle
+            .pending_within(SimTime::from_seconds(25)..SimTime::from_seconds(40))
+            .await
+            .unwrap();
+        assert_eq!(window.len(), 1);
+        assert_eq!(window[0].id(), late);
+        assert!(handle
+            .pending_within(SimTime::from_seconds(21)..SimTime::from_seconds(30))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn count_limits_recurrence_and_marks_final_occurrence() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(
+                TestEvent::every("tick", SimTime::from_seconds(1), SimDuration::seconds(1))
+                    .times(3),
+            )
+            .await
+            .unwrap();
+
+        clock.write().await.advance_by(SimDuration::seconds(10));
+        let mut fired = Vec::new();
+        for _ in 0..3 {
+            let notification = notifications.recv().await.unwrap();
+            fired.push((
+                notification.time().as_seconds(),
+                notification.occurrence(),
+                notification.is_final(),
+            ));
+        }
+        assert_eq!(fired, [(1, 0, false), (2, 1, false), (3, 2, true)]);
+        assert_eq!(handle.pending_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn one_shot_is_final_and_recurring_is_not() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("once", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(2),
+                SimDuration::seconds(1),
+            ))
+            .await
+            .unwrap();
+
+        clock.write().await.advance_by(SimDuration::seconds(3));
+        let once = notifications.recv().await.unwrap();
+        assert_eq!((once.occurrence(), once.is_final()), (0, true));
+        for occurrence in 0..2 {
+            let tick = notifications.recv().await.unwrap();
+            assert_eq!((tick.occurrence(), tick.is_final()), (occurrence, false));
+        }
+    }
+
+    async fn simultaneous_firing_order() -> Vec<String> {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::new(Arc::clone(&clock), sender);
+        let time = SimTime::from_seconds(1);
+        for (name, priority) in [("c", 0), ("a", 0), ("b", 5), ("d", -1)] {
+            handle
+                .schedule(OneShotEvent::new(name, time).with_priority(priority))
+                .await
+                .unwrap();
+        }
+
+        clock.write().await.advance_by(SimDuration::seconds(1));
+        let mut fired = Vec::new();
+        for _ in 0..4 {
+            fired.push(notifications.recv().await.unwrap().name().to_string());
+        }
+        fired
+    }
+
+    #[tokio::test]
+    async fn simultaneous_events_fire_by_priority_then_schedule_order() {
+        let first = simultaneous_firing_order().await;
+        assert_eq!(first, ["b", "c", "a", "d"]);
+        assert_eq!(simultaneous_firing_order().await, first);
+    }
+
+    #[tokio::test]
+    async fn stop_resolves_after_actor_exits() {
+        let (_clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+
+        handle.stop().await.unwrap();
+        // The actor dropped its notification sender on exit.
+        assert!(notifications.recv().await.is_none());
+        assert!(matches!(
+            handle
+                .schedule(TestEvent::once("b", SimTime::from_seconds(2)))
+                .await,
+            Err(ToolboxError::ActorClosed(_))
+        ));
+        handle.stop().await.unwrap();
+        assert_eq!(handle.join().await, ExitReason::Stopped);
+    }
+
+    fn unfired_names(report: &ShutdownReport<TestEvent>) -> Vec<(String, u64)> {
+        report
+            .pending
+            .iter()
+            .map(|unfired| (unfired.event.name.clone(), unfired.occurrence))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn immediate_shutdown_returns_unfired_events_in_order() {
+        let (_clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("late", SimTime::from_seconds(9)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(1),
+                SimDuration::seconds(1),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(handle.exit_reason(), None);
+
+        let report = handle.shutdown(ShutdownMode::Immediate).await.unwrap();
+        assert_eq!(
+            unfired_names(&report),
+            [("tick".to_string(), 0), ("late".to_string(), 0)]
+        );
+        assert_eq!(report.reason, ExitReason::Shutdown);
+        assert!(notifications.recv().await.is_none());
+        assert_eq!(handle.exit_reason(), Some(ExitReason::Shutdown));
+        assert!(matches!(
+            handle.shutdown(ShutdownMode::Immediate).await,
+            Err(ToolboxError::ActorClosed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn flush_shutdown_fires_events_before_deadline_without_moving_clock() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(1),
+                SimDuration::seconds(1),
+            ))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("late", SimTime::from_seconds(9)))
+            .await
+            .unwrap();
+
+        let report = handle
+            .shutdown(ShutdownMode::FlushUntil(SimTime::from_seconds(3)))
+            .await
+            .unwrap();
+        let mut fired = Vec::new();
+        while let Some(notification) = notifications.recv().await {
+            fired.push((
+                notification.name().to_string(),
+                notification.time().as_seconds(),
+            ));
+        }
+        assert_eq!(
+            fired,
+            [
+                ("tick".to_string(), 1),
+                ("tick".to_string(), 2),
+                ("tick".to_string(), 3)
+            ]
+        );
+        assert_eq!(
+            unfired_names(&report),
+            [("tick".to_string(), 3), ("late".to_string(), 0)]
+        );
+        assert_eq!(clock.read().await.now(), SimTime::zero());
+    }
+
+    #[tokio::test]
+    async fn fire_until_shutdown_waits_for_clock_to_pass_deadline() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("b", SimTime::from_seconds(2)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("c", SimTime::from_seconds(5)))
+            .await
+            .unwrap();
+
+        let shutdown = handle.shutdown(ShutdownMode::FireUntil(SimTime::from_seconds(2)));
+        let advance = async {
+            clock.write().await.advance_by(SimDuration::seconds(1));
+            assert_eq!(next_notification(&mut notifications).await.0, "a");
+            assert_eq!(handle.exit_reason(), None);
+            clock.write().await.advance_by(SimDuration::seconds(1));
+            assert_eq!(next_notification(&mut notifications).await.0, "b");
+        };
+        let (report, ()) = tokio::join!(shutdown, advance);
+        let report = report.unwrap();
+        assert_eq!(unfired_names(&report), [("c".to_string(), 0)]);
+        assert_eq!(report.reason, ExitReason::Shutdown);
+    }
+
+    #[tokio::test]
+    async fn second_shutdown_is_rejected_while_first_is_pending() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("b", SimTime::from_seconds(5)))
+            .await
+            .unwrap();
+
+        let first = handle.shutdown(ShutdownMode::FireUntil(SimTime::from_seconds(1)));
+        let second = async {
+            tokio::task::yield_now().await;
+            let second = handle.shutdown(ShutdownMode::Immediate).await;
+            assert!(matches!(second, Err(ToolboxError::ShutdownPending)));
+            let joined = handle.join();
+            let advance = async {
+                clock.write().await.advance_by(SimDuration::seconds(1));
+                assert_eq!(next_notification(&mut notifications).await.0, "a");
+                assert!(notifications.recv().await.is_none());
+                // Readable while another caller is waiting in `join`.
+                tokio::time::timeout(tokio::time::Duration::from_secs(1), async {
+                    while handle.exit_reason().is_none() {
+                        tokio::task::yield_now().await;
+                    }
+                })
+                .await
+                .unwrap();
+            };
+            tokio::join!(joined, advance).0
+        };
+        let (report, joined) = tokio::join!(first, second);
+        let report = report.unwrap();
+        assert_eq!(unfired_names(&report), [("b".to_string(), 0)]);
+        assert_eq!(joined, ExitReason::Shutdown);
+        assert_eq!(handle.exit_reason(), Some(ExitReason::Shutdown));
+    }
+
+    #[tokio::test]
+    async fn dropped_notification_receiver_fails_actor() {
+        let (clock, handle, notifications) = manual_scheduler();
+        drop(notifications);
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        clock.write().await.advance_by(SimDuration::seconds(1));
+        assert!(matches!(handle.join().await, ExitReason::Failed(_)));
+    }
+
+    async fn next_notification<C: Clock<Time = SimTime>>(
+        notifications: &mut Notifications<C>,
+    ) -> (String, u64) {
+        let notification = notifications.recv().await.unwrap();
+        (
+            notification.name().to_string(),
+            notification.time().as_seconds(),
+        )
+    }
+
+    #[tokio::test]
+    async fn forward_seek_fires_passed_events_by_default() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(10)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(5),
+                SimDuration::seconds(5),
+            ))
+            .await
+            .unwrap();
+        tokio::task::yield_now().await;
+
+        clock.write().await.seek(SimTime::from_seconds(12));
+        assert_eq!(
+            next_notification(&mut notifications).await,
+            ("tick".into(), 5)
+        );
+        assert_eq!(
+            next_notification(&mut notifications).await,
+            ("a".into(), 10)
+        );
+        assert_eq!(
+            next_notification(&mut notifications).await,
+            ("tick".into(), 10)
+        );
+    }
+
+    #[tokio::test]
+    async fn forward_seek_can_skip_passed_events() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::with_options(
+            Arc::clone(&clock),
+            sender,
+            SchedulerOptions {
+                seek_policy: SeekPolicy::Skip,
+                ..Default::default()
+            },
+        );
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(10)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(5),
+                SimDuration::seconds(5),
+            ))
+            .await
+            .unwrap();
+        tokio::task::yield_now().await;
+
+        clock.write().await.seek(SimTime::from_seconds(22));
+        tokio::task::yield_now().await;
+        clock.write().await.advance_to(SimTime::from_seconds(25));
+        assert_eq!(
+            next_notification(&mut notifications).await,
+            ("tick".into(), 25)
+        );
+        assert!(notifications.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn backward_seek_rearms_recurring_events() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(5),
+                SimDuration::seconds(5),
+            ))
+            .await
+            .unwrap();
+        clock.write().await.advance_to(SimTime::from_seconds(20));
+        for seconds in [5, 10, 15, 20] {
+            assert_eq!(
+                next_notification(&mut notifications).await,
+                ("tick".into(), seconds)
+            );
+        }
+
+        clock.write().await.seek(SimTime::from_seconds(7));
+        tokio::task::yield_now().await;
+        clock.write().await.advance_to(SimTime::from_seconds(10));
+        assert_eq!(
+            next_notification(&mut notifications).await,
+            ("tick".into(), 10)
+        );
+    }
+
+    /// Move a running `clock` forwards and then backwards with [`SimClock::offset_by`].
+    async fn backward_offset_rearms_recurring_events<C>(clock: C)
+    where
+        C: SimClock + Clock<Time = SimTime> + Send + Sync + 'static,
+    {
+        let clock = Arc::new(RwLock::new(clock));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle: SchedulerHandle<C, TestEvent> =
+            SchedulerHandle::new(Arc::clone(&clock), sender);
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(5),
+                SimDuration::seconds(5),
+            ))
+            .await
+            .unwrap();
+        for seconds in [5, 10, 15, 20] {
+            clock
+                .write()
+                .await
+                .offset_by(TimeDuration::milliseconds(5_000));
+            assert_eq!(
+                next_notification(&mut notifications).await,
+                ("tick".into(), seconds)
+            );
+        }
+
+        clock
+            .write()
+            .await
+            .offset_by(TimeDuration::milliseconds(-13_000));
+        tokio::task::yield_now().await;
+        clock
+            .write()
+            .await
+            .offset_by(TimeDuration::milliseconds(3_000));
+        assert_eq!(
+            next_notification(&mut notifications).await,
+            ("tick".into(), 10)
+        );
+    }
+
+    #[tokio::test]
+    async fn backward_offset_rearms_recurring_events_on_manual_clock() {
+        backward_offset_rearms_recurring_events(ManualClock::default()).await;
+    }
+
+    #[tokio::test]
+    async fn backward_offset_rearms_recurring_events_on_step_clock() {
+        backward_offset_rearms_recurring_events(running_step_clock()).await;
+    }
+
+    #[tokio::test]
+    async fn manual_advance_fires_every_recurring_occurrence() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(60),
+                SimDuration::seconds(60),
+            ))
+            .await
+            .unwrap();
+
+        clock
+            .write()
+            .await
+            .advance_to(SimTime::from_seconds(3 * 3600));
+
+        for minute in 1..=180 {
+            let notification = notifications.recv().await.unwrap();
+            assert_eq!(notification.time(), SimTime::from_seconds(minute * 60));
+        }
+        assert!(notifications.try_recv().is_err());
+    }
+
+    fn running_step_clock() -> StepSimClock {
+        let mut clock = StepSimClock::default();
+        clock.start(WallTime::now(), SimTime::zero(), TimeDuration::zero(), 1.0);
+        clock.resume();
+        clock
+    }
+
+    #[tokio::test]
+    async fn large_step_fires_every_recurring_occurrence() {
+        let clock = Arc::new(RwLock::new(running_step_clock()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle: SchedulerHandle<StepSimClock, TestEvent> =
+            SchedulerHandle::new(Arc::clone(&clock), sender);
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(60),
+                SimDuration::seconds(60),
+            ))
+            .await
+            .unwrap();
+
+        clock.write().await.step(SimDuration::seconds(600));
+
+        for minute in 1..=10 {
+            let notification = notifications.recv().await.unwrap();
+            assert_eq!(notification.time(), SimTime::from_seconds(minute * 60));
+        }
+        assert!(notifications.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn discrete_mode_steps_a_step_clock() {
+        let mut clock = running_step_clock();
+        clock.pause();
+        let clock = Arc::new(RwLock::new(clock));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle: SchedulerHandle<StepSimClock, TestEvent> = SchedulerHandle::new_discrete(
+            Arc::clone(&clock),
+            sender,
+            DiscreteEventOptions {
+                stop_time: Some(SimTime::from_seconds(600)),
+                ..Default::default()
+            },
+        );
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(60),
+                SimDuration::seconds(60),
+            ))
+            .await
+            .unwrap();
+        clock.write().await.resume();
+
+        let mut fired = 0;
+        while let Some(notification) = notifications.recv().await {
+            fired += 1;
+            assert_eq!(notification.time(), SimTime::from_seconds(fired * 60));
+        }
+        assert_eq!(fired, 10);
+        assert_eq!(clock.read().await.now(), SimTime::from_seconds(600));
+    }
+
+    #[tokio::test]
+    async fn discrete_mode_jumps_to_each_event_until_stop_time() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        clock.write().await.pause();
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::new_discrete(
+            Arc::clone(&clock),
+            sender,
+            DiscreteEventOptions {
+                stop_time: Some(SimTime::from_seconds(3600)),
+                drain: None,
+                ..Default::default()
+            },
+        );
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(60),
+                SimDuration::seconds(60),
+            ))
+            .await
+            .unwrap();
+        clock.write().await.resume();
+
+        let mut fired = 0;
+        while let Some(notification) = notifications.recv().await {
+            fired += 1;
+            assert_eq!(notification.time(), SimTime::from_seconds(fired * 60));
+        }
+        assert_eq!(fired, 60);
+        assert_eq!(clock.read().await.now(), SimTime::from_seconds(3600));
+        assert_eq!(handle.join().await, ExitReason::Completed);
+    }
+
+    #[tokio::test]
+    async fn discrete_mode_runs_on_timing_wheel() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        clock.write().await.pause();
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::new_discrete(
+            Arc::clone(&clock),
+            sender,
+            DiscreteEventOptions {
+                stop_time: Some(SimTime::from_seconds(300)),
+                ..Default::default()
+            },
+        )
+        .with_queue(QueueBackend::TimingWheel);
+        handle
+            .schedule(TestEvent::every(
+                "tick",
+                SimTime::from_seconds(60),
+                SimDuration::seconds(60),
+            ))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(90)))
+            .await
+            .unwrap();
+        let id = handle
+            .schedule(TestEvent::once("b", SimTime::from_seconds(150)))
+            .await
+            .unwrap();
+        assert_eq!(handle.cancel(id).await.unwrap(), 1);
+        clock.write().await.resume();
+
+        let mut fired = Vec::new();
+        while let Some(notification) = notifications.recv().await {
+            fired.push((
+                notification.name().to_string(),
+                notification.time().as_seconds(),
+            ));
+        }
+        assert_eq!(
+            fired,
+            [
+                ("tick".to_string(), 60),
+                ("a".to_string(), 90),
+                ("tick".to_string(), 120),
+                ("tick".to_string(), 180),
+                ("tick".to_string(), 240),
+                ("tick".to_string(), 300),
+            ]
+        );
+    }
+
+    /// Clock counting plain `u64` steps, whose time is not a [`TickTime`](crate::time::TickTime).
+    #[derive(Default)]
+    struct StepCounter(u64);
+
+    impl Clock for StepCounter {
+        type Time = u64;
+
+        fn now(&self) -> u64 {
+            self.0
+        }
+
+        fn delay_time(&self, _then: u64) -> TimeDuration {
+            TimeDuration::zero()
+        }
+    }
+
+    impl DiscreteClock for StepCounter {
+        fn jump_to(&mut self, time: u64) {
+            self.0 = time;
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Step(u64);
+
+    impl Event<StepCounter> for Step {
+        fn name(&self) -> &str {
+            "step"
+        }
+
+        fn execution_time(&self) -> u64 {
+            self.0
+        }
+
+        fn next_time(&self) -> Self {
+            Step(self.0 + 10)
+        }
+
+        fn count(&self, _new_count: u64) -> Self {
+            Step(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn heap_queue_runs_on_any_ordered_time() {
+        let clock = Arc::new(RwLock::new(StepCounter::default()));
+        let (sender, mut notifications) =
+            mpsc::unbounded_channel::<EventNotification<StepCounter>>();
+        let handle = SchedulerHandle::new_discrete(
+            Arc::clone(&clock),
+            sender,
+            DiscreteEventOptions {
+                stop_time: Some(25),
+                ..Default::default()
+            },
+        );
+        handle.schedule(Step(5)).await.unwrap();
+
+        let mut fired = Vec::new();
+        while let Some(notification) = notifications.recv().await {
+            fired.push(notification.time());
+        }
+        assert_eq!(fired, [5, 15, 25]);
+        assert_eq!(clock.read().await.now(), 25);
+    }
+
+    #[tokio::test]
+    async fn discrete_mode_waits_for_consumers_to_drain() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        clock.write().await.pause();
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let drain = DrainTracker::new();
+        let handle = SchedulerHandle::new_discrete(
+            Arc::clone(&clock),
+            sender,
+            DiscreteEventOptions {
+                stop_time: Some(SimTime::from_seconds(10)),
+                drain: Some(drain.clone()),
+                ..Default::default()
+            },
+        );
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("b", SimTime::from_seconds(5)))
+            .await
+            .unwrap();
+        clock.write().await.resume();
+
+        let mut fired = Vec::new();
+        while let Some(notification) = notifications.recv().await {
+            tokio::task::yield_now().await;
+            assert_eq!(clock.read().await.now(), notification.time());
+            fired.push(notification.name().to_string());
+            if notification.name() == "a" {
+                handle
+                    .schedule(TestEvent::once("follow-up", SimTime::from_seconds(2)))
+                    .await
+                    .unwrap();
+            }
+            drain.done();
+        }
+        assert_eq!(fired, ["a", "follow-up", "b"]);
+        assert_eq!(clock.read().await.now(), SimTime::from_seconds(5));
+    }
+
+    #[tokio::test]
+    async fn discrete_mode_only_drains_opted_in_subscribers() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        clock.write().await.pause();
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let drain = DrainTracker::new();
+        let handle = SchedulerHandle::new_discrete(
+            Arc::clone(&clock),
+            sender,
+            DiscreteEventOptions {
+                stop_time: Some(SimTime::from_seconds(10)),
+                drain: Some(drain.clone()),
+                ..Default::default()
+            },
+        );
+        // Never read, and must not hold the clock back.
+        let _unaware = handle.subscribe(NotificationFilter::All).await.unwrap();
+        let (drained_sender, mut drained) = mpsc::unbounded_channel();
+        handle
+            .subscribe_drained(NotificationFilter::Name("a".to_string()), drained_sender)
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        handle
+            .schedule(TestEvent::once("b", SimTime::from_seconds(5)))
+            .await
+            .unwrap();
+        clock.write().await.resume();
+
+        let a = notifications.recv().await.unwrap();
+        drain.done();
+        assert_eq!(drained.recv().await.unwrap().name(), "a");
+        tokio::task::yield_now().await;
+        // Still waiting for the drained subscriber.
+        assert_eq!(drain.outstanding(), 1);
+        assert_eq!(clock.read().await.now(), a.time());
+        drain.done();
+
+        assert_eq!(notifications.recv().await.unwrap().name(), "b");
+        drain.done();
+        assert!(notifications.recv().await.is_none());
+        assert_eq!(clock.read().await.now(), SimTime::from_seconds(5));
+    }
+
+    #[tokio::test]
+    async fn discrete_mode_rejects_events_in_the_past() {
+        let clock = Arc::new(RwLock::new(ManualClock::new(SimTime::from_seconds(5))));
+        clock.write().await.pause();
+        let (sender, _notifications) = mpsc::unbounded_channel();
+        let handle: SchedulerHandle<ManualClock, TestEvent> = SchedulerHandle::new_discrete(
+            Arc::clone(&clock),
+            sender,
+            DiscreteEventOptions::default(),
+        );
+
+        assert!(matches!(
+            handle
+                .schedule(TestEvent::once("late", SimTime::from_seconds(1)))
+                .await,
+            Err(ToolboxError::EventRejected(_))
+        ));
+        let id = handle
+            .schedule(TestEvent::once("on-time", SimTime::from_seconds(5)))
+            .await
+            .unwrap();
+        assert!(matches!(
+            handle
+                .replace(id, TestEvent::once("late", SimTime::from_seconds(1)))
+                .await,
+            Err(ToolboxError::EventRejected(_))
+        ));
+    }
+
+    // Time is paused in these tests, so a sleep only returns once the scheduler is idle and a
+    // scheduler that waits for its own timer instead of the clock would move time on.
+
+    #[tokio::test(start_paused = true)]
+    async fn paused_clock_holds_events_until_resumed() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_millis(50)))
+            .await
+            .unwrap();
+        clock.write().await.pause();
+        clock
+            .write()
+            .await
+            .offset_by(TimeDuration::milliseconds(150));
+
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        assert!(notifications.try_recv().is_err());
+
+        clock.write().await.resume();
+        let resumed = tokio::time::Instant::now();
+        assert_eq!(notifications.recv().await.unwrap().name(), "a");
+        assert_eq!(resumed.elapsed(), tokio::time::Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn offset_clock_wakes_scheduler_immediately() {
+        let (clock, handle, mut notifications) = manual_scheduler();
+        handle
+            .schedule(TestEvent::once("a", SimTime::from_seconds(60)))
+            .await
+            .unwrap();
+        tokio::task::yield_now().await;
+
+        let offset = tokio::time::Instant::now();
+        clock
+            .write()
+            .await
+            .offset_by(TimeDuration::milliseconds(60_000));
+        let notification = notifications.recv().await.unwrap();
+        assert_eq!(notification.time(), SimTime::from_seconds(60));
+        assert_eq!(offset.elapsed(), tokio::time::Duration::ZERO);
+    }
 }
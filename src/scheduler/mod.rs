@@ -1,2 +1,10 @@
 pub mod event;
+pub mod handler;
 pub mod internal;
+pub mod notification;
+pub mod persistence;
+mod queue;
+pub mod shutdown;
+pub mod standard_events;
+pub mod subscription;
+mod timing_wheel;
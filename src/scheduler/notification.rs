@@ -0,0 +1,464 @@
+//! This module contains the channels a scheduler delivers [`EventNotification`]s on.
+//!
+//! A scheduler accepts either an unbounded tokio sender or the sender half of a
+//! [`bounded_channel`], which holds a fixed number of notifications and applies an
+//! [`OverflowPolicy`] once a slow consumer lets it fill up.
+//!
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use tokio::sync::{mpsc, mpsc::error::TryRecvError, Notify};
+
+use crate::{
+    error::{ToolboxError, ToolboxResult},
+    time::Clock,
+};
+
+use super::event::EventNotification;
+
+/// What a [`bounded_channel`] does with a notification that arrives while it is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Hold the scheduler until the consumer makes room. Nothing is dropped. Commands sent
+    /// meanwhile are applied once there is room, except stop and shutdown requests, which are
+    /// honoured at once and leave the waiting occurrence pending.
+    #[default]
+    Block,
+    /// Drop the new notification.
+    DropNewest,
+    /// Drop the oldest waiting notification to make room for the new one.
+    DropOldest,
+    /// Remove the most recent waiting notification with the same event name and queue the new
+    /// one in its place, counting the removed one in [`EventNotification::missed`]. If no waiting
+    /// notification has the same name, the oldest waiting notification is dropped to make room,
+    /// as with [`OverflowPolicy::DropOldest`].
+    CoalesceByName,
+}
+
+struct Shared<T: Clock, P> {
+    queue: Mutex<VecDeque<EventNotification<T, P>>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+    sender_closed: AtomicBool,
+    receiver_closed: AtomicBool,
+    readable: Notify,
+    writable: Notify,
+}
+
+/// Create a channel holding up to `capacity` notifications, handling overflow with `policy`.
+/// A capacity of zero is treated as one.
+pub fn bounded_channel<T: Clock, P>(
+    capacity: usize,
+    policy: OverflowPolicy,
+) -> (NotificationSender<T, P>, NotificationReceiver<T, P>) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::new()),
+        capacity: capacity.max(1),
+        policy,
+        dropped: AtomicU64::new(0),
+        sender_closed: AtomicBool::new(false),
+        receiver_closed: AtomicBool::new(false),
+        readable: Notify::new(),
+        writable: Notify::new(),
+    });
+    (
+        NotificationSender {
+            inner: SenderKind::Bounded(BoundedSender {
+                shared: Arc::clone(&shared),
+            }),
+        },
+        NotificationReceiver { shared },
+    )
+}
+
+/// The sending half used by a scheduler to deliver notifications.
+pub struct NotificationSender<T: Clock, P = ()> {
+    inner: SenderKind<T, P>,
+}
+
+enum SenderKind<T: Clock, P> {
+    Unbounded(mpsc::UnboundedSender<EventNotification<T, P>>),
+    Bounded(BoundedSender<T, P>),
+}
+
+struct BoundedSender<T: Clock, P> {
+    shared: Arc<Shared<T, P>>,
+}
+
+impl<T: Clock, P> From<mpsc::UnboundedSender<EventNotification<T, P>>>
+    for NotificationSender<T, P>
+{
+    fn from(sender: mpsc::UnboundedSender<EventNotification<T, P>>) -> Self {
+        Self {
+            inner: SenderKind::Unbounded(sender),
+        }
+    }
+}
+
+impl<T: Clock, P> NotificationSender<T, P> {
+    /// Deliver a notification, waiting for room when a bounded channel blocks on overflow.
+    ///
+    /// # Returns
+    /// `true` if the number of notifications waiting for the consumer grew, `false` if the
+    /// notification was dropped or took the place of one already waiting.
+    ///
+    /// # Errors
+    /// [`ToolboxError::ActorClosed`] once the receiver has been dropped.
+    pub async fn send(&self, notification: EventNotification<T, P>) -> ToolboxResult<bool> {
+        match &self.inner {
+            SenderKind::Unbounded(sender) => {
+                sender.send(notification).map(|_| true).map_err(|_| {
+                    ToolboxError::ActorClosed("notification receiver dropped".to_string())
+                })
+            }
+            SenderKind::Bounded(sender) => sender.send(notification).await,
+        }
+    }
+
+    /// Returns `true` once the receiver has been dropped.
+    pub fn is_closed(&self) -> bool {
+        match &self.inner {
+            SenderKind::Unbounded(sender) => sender.is_closed(),
+            SenderKind::Bounded(sender) => sender.shared.receiver_closed.load(Ordering::Acquire),
+        }
+    }
+}
+
+impl<T: Clock, P> BoundedSender<T, P> {
+    async fn send(&self, mut notification: EventNotification<T, P>) -> ToolboxResult<bool> {
+        let shared = &self.shared;
+        loop {
+            let writable = shared.writable.notified();
+            {
+                let mut queue = shared.queue.lock().expect("notification queue poisoned");
+                if shared.receiver_closed.load(Ordering::Acquire) {
+                    return Err(ToolboxError::ActorClosed(
+                        "notification receiver dropped".to_string(),
+                    ));
+                }
+                if queue.len() < shared.capacity {
+                    queue.push_back(notification);
+                    shared.readable.notify_one();
+                    return Ok(true);
+                }
+                match shared.policy {
+                    OverflowPolicy::Block => {}
+                    OverflowPolicy::DropNewest => {
+                        shared.dropped.fetch_add(1, Ordering::Relaxed);
+                        return Ok(false);
+                    }
+                    OverflowPolicy::DropOldest => {
+                        queue.pop_front();
+                        queue.push_back(notification);
+                        shared.dropped.fetch_add(1, Ordering::Relaxed);
+                        return Ok(false);
+                    }
+                    OverflowPolicy::CoalesceByName => {
+                        // Move the replacement to the back to keep notifications in time order.
+                        match queue
+                            .iter()
+                            .rposition(|waiting| waiting.name == notification.name)
+                            .and_then(|position| queue.remove(position))
+                        {
+                            Some(waiting) => notification.missed += waiting.missed + 1,
+                            None => _ = queue.pop_front(),
+                        }
+                        queue.push_back(notification);
+                        shared.dropped.fetch_add(1, Ordering::Relaxed);
+                        return Ok(false);
+                    }
+                }
+            }
+            writable.await;
+        }
+    }
+}
+
+impl<T: Clock, P> Drop for BoundedSender<T, P> {
+    fn drop(&mut self) {
+        self.shared.sender_closed.store(true, Ordering::Release);
+        self.shared.readable.notify_one();
+    }
+}
+
+/// The receiving half of a [`bounded_channel`].
+pub struct NotificationReceiver<T: Clock, P = ()> {
+    shared: Arc<Shared<T, P>>,
+}
+
+impl<T: Clock, P> NotificationReceiver<T, P> {
+    /// Receive the next notification, or `None` once the scheduler has stopped and every
+    /// waiting notification has been received.
+    pub async fn recv(&mut self) -> Option<EventNotification<T, P>> {
+        loop {
+            match self.try_recv() {
+                Ok(notification) => return Some(notification),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => self.shared.readable.notified().await,
+            }
+        }
+    }
+
+    /// Receive a waiting notification without waiting.
+    pub fn try_recv(&mut self) -> Result<EventNotification<T, P>, TryRecvError> {
+        let mut queue = self
+            .shared
+            .queue
+            .lock()
+            .expect("notification queue poisoned");
+        match queue.pop_front() {
+            Some(notification) => {
+                self.shared.writable.notify_one();
+                Ok(notification)
+            }
+            None if self.shared.sender_closed.load(Ordering::Acquire) => {
+                Err(TryRecvError::Disconnected)
+            }
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Number of notifications dropped or coalesced because the channel was full.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Number of notifications waiting to be received.
+    pub fn len(&self) -> usize {
+        self.shared
+            .queue
+            .lock()
+            .expect("notification queue poisoned")
+            .len()
+    }
+
+    /// Returns `true` if no notifications are waiting to be received.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Clock, P> Drop for NotificationReceiver<T, P> {
+    fn drop(&mut self) {
+        self.shared.receiver_closed.store(true, Ordering::Release);
+        self.shared.writable.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod notification_tests {
+    use std::sync::Arc;
+
+    use tokio::sync::{mpsc, RwLock};
+    use tokio::time::{timeout, Duration};
+
+    use crate::scheduler::event::{Event, EventNotification};
+    use crate::scheduler::handler::HandlerTarget;
+    use crate::scheduler::internal::SchedulerHandle;
+    use crate::scheduler::notification::{bounded_channel, NotificationReceiver, OverflowPolicy};
+    use crate::scheduler::shutdown::ShutdownMode;
+    use crate::scheduler::standard_events::OneShotEvent;
+    use crate::scheduler::subscription::NotificationFilter;
+    use crate::time::{ManualClock, SimDuration, SimTime};
+
+    type Handle = SchedulerHandle<ManualClock, OneShotEvent<ManualClock>>;
+
+    async fn overflow(
+        capacity: usize,
+        policy: OverflowPolicy,
+    ) -> (
+        Arc<RwLock<ManualClock>>,
+        Handle,
+        NotificationReceiver<ManualClock>,
+    ) {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, receiver) = bounded_channel(capacity, policy);
+        let handle = SchedulerHandle::new(Arc::clone(&clock), sender);
+        for (name, seconds) in [("a", 1), ("b", 2), ("a", 3), ("c", 4)] {
+            handle
+                .schedule(OneShotEvent::new(name, SimTime::from_seconds(seconds)))
+                .await
+                .unwrap();
+        }
+        clock.write().await.advance_by(SimDuration::seconds(4));
+        (clock, handle, receiver)
+    }
+
+    async fn received(
+        handle: Handle,
+        mut receiver: NotificationReceiver<ManualClock>,
+    ) -> (Vec<(String, u64, u64)>, u64) {
+        while handle.pending_count().await.unwrap() > 0 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        handle.stop().await.unwrap();
+        let mut fired = Vec::new();
+        while let Some(notification) = receiver.recv().await {
+            fired.push((
+                notification.name().to_string(),
+                notification.time().as_seconds(),
+                notification.missed(),
+            ));
+        }
+        (fired, receiver.dropped())
+    }
+
+    fn fired(expected: &[(&str, u64, u64)]) -> Vec<(String, u64, u64)> {
+        expected
+            .iter()
+            .map(|(name, time, missed)| (name.to_string(), *time, *missed))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn full_channel_applies_overflow_policy() {
+        let (_clock, handle, receiver) = overflow(2, OverflowPolicy::DropNewest).await;
+        assert_eq!(
+            received(handle, receiver).await,
+            (fired(&[("a", 1, 0), ("b", 2, 0)]), 2)
+        );
+
+        let (_clock, handle, receiver) = overflow(2, OverflowPolicy::DropOldest).await;
+        assert_eq!(
+            received(handle, receiver).await,
+            (fired(&[("a", 3, 0), ("c", 4, 0)]), 2)
+        );
+
+        let (_clock, handle, receiver) = overflow(2, OverflowPolicy::CoalesceByName).await;
+        assert_eq!(
+            received(handle, receiver).await,
+            (fired(&[("a", 3, 1), ("c", 4, 0)]), 2)
+        );
+    }
+
+    #[tokio::test]
+    async fn coalescing_a_new_name_drops_the_oldest_notification() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, receiver) = bounded_channel(2, OverflowPolicy::CoalesceByName);
+        let handle = SchedulerHandle::new(Arc::clone(&clock), sender);
+        for (name, seconds) in [("a", 1), ("b", 2), ("c", 3)] {
+            handle
+                .schedule(OneShotEvent::new(name, SimTime::from_seconds(seconds)))
+                .await
+                .unwrap();
+        }
+        clock.write().await.advance_by(SimDuration::seconds(3));
+        assert_eq!(
+            received(handle, receiver).await,
+            (fired(&[("b", 2, 0), ("c", 3, 0)]), 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn blocking_channel_waits_for_consumer_without_locking_clock() {
+        let (clock, _handle, mut receiver) = overflow(1, OverflowPolicy::Block).await;
+        while receiver.is_empty() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(receiver.len(), 1);
+
+        // The scheduler is waiting for room but must not hold the clock.
+        timeout(Duration::from_secs(1), clock.write())
+            .await
+            .expect("clock should not be locked while the scheduler waits")
+            .advance_by(SimDuration::seconds(1));
+
+        let mut times = Vec::new();
+        for _ in 0..4 {
+            times.push(receiver.recv().await.unwrap().time().as_seconds());
+        }
+        assert_eq!(times, [1, 2, 3, 4]);
+        assert_eq!(receiver.dropped(), 0);
+    }
+
+    #[tokio::test]
+    async fn shutdown_is_honoured_while_blocked_on_full_channel() {
+        let (_clock, handle, receiver) = overflow(1, OverflowPolicy::Block).await;
+        while receiver.is_empty() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        let report = timeout(
+            Duration::from_secs(1),
+            handle.shutdown(ShutdownMode::Immediate),
+        )
+        .await
+        .expect("shutdown should not wait for the consumer")
+        .unwrap();
+        let unfired: Vec<_> = report
+            .pending
+            .iter()
+            .map(|unfired| unfired.event.execution_time().as_seconds())
+            .collect();
+        assert_eq!(unfired, [2, 3, 4]);
+        assert_eq!(receiver.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn abandoned_notification_reaches_every_target_once() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut receiver) = bounded_channel(1, OverflowPolicy::Block);
+        let handle: Handle = SchedulerHandle::new(Arc::clone(&clock), sender);
+        let (handled_sender, mut handled) = mpsc::unbounded_channel();
+        handle
+            .add_handler(
+                HandlerTarget::Name("tick".to_string()),
+                move |notification: EventNotification<ManualClock>| {
+                    let handled_sender = handled_sender.clone();
+                    async move {
+                        _ = handled_sender.send(notification.time().as_seconds());
+                        Ok(())
+                    }
+                },
+            )
+            .await
+            .unwrap();
+        let mut subscribed = handle
+            .subscribe(NotificationFilter::Name("tick".to_string()))
+            .await
+            .unwrap();
+        for seconds in [1, 2] {
+            handle
+                .schedule(OneShotEvent::new("tick", SimTime::from_seconds(seconds)))
+                .await
+                .unwrap();
+        }
+        clock.write().await.advance_by(SimDuration::seconds(2));
+        while receiver.is_empty() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        // The second notification is waiting for room when the shutdown arrives, and is fired
+        // again by the flush.
+        let shutdown = tokio::spawn(async move {
+            handle
+                .shutdown(ShutdownMode::FlushUntil(SimTime::from_seconds(2)))
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let mut times = Vec::new();
+        while let Some(notification) = receiver.recv().await {
+            times.push(notification.time().as_seconds());
+        }
+        assert!(shutdown.await.unwrap().unwrap().pending.is_empty());
+
+        assert_eq!(times, [1, 2]);
+        let mut times = Vec::new();
+        while let Some(time) = handled.recv().await {
+            times.push(time);
+        }
+        assert_eq!(times, [1, 2]);
+        let mut times = Vec::new();
+        while let Ok(notification) = subscribed.try_recv() {
+            times.push(notification.time().as_seconds());
+        }
+        assert_eq!(times, [1, 2]);
+    }
+}
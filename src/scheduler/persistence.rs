@@ -0,0 +1,688 @@
+//! This module contains the journal that lets a scheduler recover its queue after a restart.
+//!
+//! A [`Journal`] appends a JSON record to a local file for every event that is scheduled,
+//! replaced, cancelled or fired. Once enough records have been appended the file is compacted
+//! into a snapshot of the queue. Opening a journal replays the file, and a scheduler created
+//! with [`SchedulerHandle::with_journal`](super::internal::SchedulerHandle::with_journal) or
+//! [`SchedulerHandle::new_discrete_with_journal`](super::internal::SchedulerHandle::new_discrete_with_journal)
+//! starts with the recovered events, handling those whose time passed while it was down
+//! according to a [`RecoveryPolicy`].
+//!
+//! Records are written by a thread of the journal's own, so the scheduler never waits for the
+//! disk. A scheduler acknowledges a command that changed its queue once the records it wrote
+//! have been synced to disk according to the journal's [`SyncPolicy`].
+//!
+//! Stopping or shutting down a scheduler leaves the journal in place, so the events pending
+//! at that point are recovered on the next start.
+//!
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, ErrorKind, Write},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::error::ToolboxResult;
+
+use super::event::{EventId, UnfiredEvent};
+
+/// What a recovering scheduler does with events whose execution time passed while it was down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryPolicy {
+    /// Queue them unchanged, so they fire as soon as the scheduler starts according to each
+    /// event's [`MisfirePolicy`](super::event::MisfirePolicy).
+    #[default]
+    Fire,
+    /// Move recurring events to their first occurrence at or after the current time and drop
+    /// every other passed event.
+    Skip,
+    /// Drop every passed event, including any later occurrences of recurring events.
+    Discard,
+}
+
+/// When a [`Journal`] forces appended records to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPolicy {
+    /// Sync every batch of appended records before acknowledging the commands that wrote them,
+    /// so an acknowledged change survives a power failure.
+    #[default]
+    Always,
+    /// Leave appended records to the operating system, which may lose the latest of them on a
+    /// power failure. Snapshots written by compaction are still synced.
+    Never,
+}
+
+/// Where and how a [`Journal`] is kept.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct JournalOptions {
+    /// File holding the journal. Compaction writes a temporary file next to it.
+    pub path: PathBuf,
+    /// Number of records appended before the journal is compacted into a snapshot of the queue.
+    #[serde(default = "default_compact_after")]
+    pub compact_after: usize,
+    /// How events that passed while the scheduler was down are recovered.
+    #[serde(default)]
+    pub recovery: RecoveryPolicy,
+    /// When appended records are forced to disk.
+    #[serde(default)]
+    pub sync: SyncPolicy,
+}
+
+fn default_compact_after() -> usize {
+    1000
+}
+
+impl JournalOptions {
+    /// Keep the journal in `path`, with the default compaction threshold and recovery policy.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            compact_after: default_compact_after(),
+            recovery: RecoveryPolicy::default(),
+            sync: SyncPolicy::default(),
+        }
+    }
+}
+
+/// A single operation on the queue, written as one line of JSON.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub(crate) enum Record<E> {
+    /// An event was queued after firing `occurrence` times.
+    Schedule {
+        id: EventId,
+        occurrence: u64,
+        event: E,
+    },
+    /// A pending event was replaced, keeping its ID and occurrence count.
+    Replace { id: EventId, event: E },
+    /// A pending event was cancelled.
+    Cancel { id: EventId },
+    /// An event fired and was re-queued as `next`, or removed if it has no next occurrence.
+    Fire {
+        id: EventId,
+        next: Option<NextOccurrence<E>>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct NextOccurrence<E> {
+    pub occurrence: u64,
+    pub event: E,
+}
+
+/// The journal as seen by the scheduler, which does not require its events to be serializable.
+pub(crate) trait QueueJournal<E>: Send {
+    /// Append a record of an operation on the queue.
+    fn append(&mut self, record: Record<&E>) -> ToolboxResult;
+    /// Returns `true` once enough records were appended to compact the journal.
+    fn needs_compaction(&self) -> bool;
+    /// Replace the journal with a snapshot of `pending` events, given in scheduling order as
+    /// `(id, occurrence, event)`.
+    fn compact(&mut self, pending: Vec<(EventId, u64, &E)>) -> ToolboxResult;
+    /// Call `then` once every record appended so far has been written and synced, or with the
+    /// error that stopped the journal.
+    fn after_writes(&mut self, then: WriteCallback) -> ToolboxResult;
+    /// How events that passed while the scheduler was down are recovered.
+    fn recovery(&self) -> RecoveryPolicy;
+}
+
+/// Called by the writer thread with the outcome of the writes queued before it.
+pub(crate) type WriteCallback = Box<dyn FnOnce(ToolboxResult) + Send>;
+
+/// Work queued for the writer thread, carried out in order.
+enum WriteOp {
+    /// Append a record.
+    Append(Vec<u8>),
+    /// Replace the journal with these records.
+    Snapshot(Vec<u8>),
+    /// Report that the operations before it are done.
+    Then(WriteCallback),
+}
+
+/// Append-only journal of the operations on a scheduler's queue.
+pub struct Journal<E> {
+    options: JournalOptions,
+    writer: mpsc::Sender<WriteOp>,
+    /// Error that stopped the writer thread.
+    failure: Arc<Mutex<Option<io::Error>>>,
+    appended: usize,
+    recovered: Vec<UnfiredEvent<E>>,
+    next_id: u64,
+    _event: PhantomData<fn(E) -> E>,
+}
+
+impl<E: Serialize + DeserializeOwned> Journal<E> {
+    /// Open the journal, creating it if it does not exist, and replay it to recover the events
+    /// that were pending. The journal is compacted straight away.
+    ///
+    /// A final record that cannot be read is assumed to be a write interrupted by a crash and is
+    /// ignored.
+    ///
+    /// # Errors
+    /// [`ToolboxError::Io`](crate::error::ToolboxError::Io) if the file cannot be read or
+    /// written, or [`ToolboxError::Serialization`](crate::error::ToolboxError::Serialization)
+    /// if any other record cannot be read.
+    pub fn open(options: JournalOptions) -> ToolboxResult<Self> {
+        let lines = match File::open(&options.path) {
+            Ok(file) => BufReader::new(file)
+                .lines()
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let (recovered, next_id) = replay(&lines)?;
+
+        let snapshot = snapshot(
+            recovered
+                .iter()
+                .map(|unfired| (unfired.id, unfired.occurrence, &unfired.event)),
+        )?;
+        let writer = Writer {
+            file: write_snapshot(&options.path, &snapshot)?,
+            path: options.path.clone(),
+            sync: options.sync,
+            dirty: false,
+        };
+        let (sender, ops) = mpsc::channel();
+        let failure = Arc::new(Mutex::new(None));
+        let writer_failure = Arc::clone(&failure);
+        thread::Builder::new()
+            .name("scheduler-journal".to_string())
+            .spawn(move || writer.run(ops, writer_failure))?;
+        Ok(Self {
+            options,
+            writer: sender,
+            failure,
+            appended: 0,
+            recovered,
+            next_id,
+            _event: PhantomData,
+        })
+    }
+
+    /// Events recovered from the journal, in the order they were scheduled.
+    pub fn recovered(&self) -> &[UnfiredEvent<E>] {
+        &self.recovered
+    }
+
+    /// First ID not used by any event in the journal.
+    pub(crate) fn next_id(&self) -> u64 {
+        self.next_id
+    }
+
+    /// Take the recovered events to queue them.
+    pub(crate) fn take_recovered(&mut self) -> Vec<UnfiredEvent<E>> {
+        std::mem::take(&mut self.recovered)
+    }
+
+    /// Queue `op` for the writer thread.
+    ///
+    /// # Errors
+    /// [`ToolboxError::Io`](crate::error::ToolboxError::Io) if the writer thread stopped on an
+    /// error.
+    fn send(&self, op: WriteOp) -> ToolboxResult {
+        if let Some(err) = lock(&self.failure).as_ref() {
+            return Err(copy_error(err).into());
+        }
+        self.writer
+            .send(op)
+            .map_err(|_| io::Error::new(ErrorKind::BrokenPipe, "journal writer stopped"))?;
+        Ok(())
+    }
+}
+
+impl<E: Serialize + DeserializeOwned + Send> QueueJournal<E> for Journal<E> {
+    fn append(&mut self, record: Record<&E>) -> ToolboxResult {
+        self.send(WriteOp::Append(line(&record)?))?;
+        self.appended += 1;
+        Ok(())
+    }
+
+    fn needs_compaction(&self) -> bool {
+        self.appended >= self.options.compact_after
+    }
+
+    fn compact(&mut self, pending: Vec<(EventId, u64, &E)>) -> ToolboxResult {
+        self.send(WriteOp::Snapshot(snapshot(pending)?))?;
+        self.appended = 0;
+        Ok(())
+    }
+
+    fn after_writes(&mut self, then: WriteCallback) -> ToolboxResult {
+        self.send(WriteOp::Then(then))
+    }
+
+    fn recovery(&self) -> RecoveryPolicy {
+        self.options.recovery
+    }
+}
+
+/// Serialize `record` as a single line.
+fn line<E: Serialize>(record: &Record<&E>) -> ToolboxResult<Vec<u8>> {
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Serialize a snapshot of `pending` events, one schedule record per line.
+fn snapshot<'a, E: Serialize + 'a>(
+    pending: impl IntoIterator<Item = (EventId, u64, &'a E)>,
+) -> ToolboxResult<Vec<u8>> {
+    let mut lines = Vec::new();
+    for (id, occurrence, event) in pending {
+        lines.extend(line(&Record::Schedule {
+            id,
+            occurrence,
+            event,
+        })?);
+    }
+    Ok(lines)
+}
+
+/// Replace the journal at `path` with `snapshot`, returning the journal opened for appending.
+fn write_snapshot(path: &Path, snapshot: &[u8]) -> io::Result<File> {
+    // Write the snapshot aside and rename it over the journal so a crash leaves one intact.
+    let mut snapshot_path = path.to_path_buf().into_os_string();
+    snapshot_path.push(".compact");
+    let mut file = File::create(&snapshot_path)?;
+    file.write_all(snapshot)?;
+    file.sync_all()?;
+    fs::rename(&snapshot_path, path)?;
+    sync_directory(path)?;
+    OpenOptions::new().append(true).open(path)
+}
+
+/// Sync the directory holding `path`, which makes a rename to `path` durable.
+#[cfg(unix)]
+fn sync_directory(path: &Path) -> io::Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(directory)?.sync_all()
+}
+
+/// Directories cannot be opened for syncing on this platform.
+#[cfg(not(unix))]
+fn sync_directory(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// `io::Error` is not `Clone`, so every caller told about a failure gets a copy.
+fn copy_error(err: &io::Error) -> io::Error {
+    io::Error::new(err.kind(), err.to_string())
+}
+
+/// Owns the journal file on the writer thread.
+struct Writer {
+    path: PathBuf,
+    file: File,
+    sync: SyncPolicy,
+    /// Appended records have not been synced yet.
+    dirty: bool,
+}
+
+impl Writer {
+    /// Carry out operations until the journal is dropped. Operations queued together are
+    /// written before the records are synced once for all of them. After a failure nothing
+    /// more is written and every callback receives the error.
+    fn run(mut self, ops: mpsc::Receiver<WriteOp>, failure: Arc<Mutex<Option<io::Error>>>) {
+        while let Ok(op) = ops.recv() {
+            let mut callbacks = Vec::new();
+            for op in std::iter::once(op).chain(ops.try_iter()) {
+                let written = match op {
+                    WriteOp::Then(then) => {
+                        callbacks.push(then);
+                        continue;
+                    }
+                    _ if lock(&failure).is_some() => continue,
+                    WriteOp::Append(line) => self.append(&line),
+                    WriteOp::Snapshot(snapshot) => self.snapshot(&snapshot),
+                };
+                if let Err(err) = written {
+                    *lock(&failure) = Some(err);
+                }
+            }
+            if lock(&failure).is_none() {
+                if let Err(err) = self.sync() {
+                    *lock(&failure) = Some(err);
+                }
+            }
+            for then in callbacks {
+                match lock(&failure).as_ref() {
+                    Some(err) => then(Err(copy_error(err).into())),
+                    None => then(Ok(())),
+                }
+            }
+        }
+    }
+
+    fn append(&mut self, line: &[u8]) -> io::Result<()> {
+        self.file.write_all(line)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn snapshot(&mut self, snapshot: &[u8]) -> io::Result<()> {
+        self.file = write_snapshot(&self.path, snapshot)?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        if self.dirty && self.sync == SyncPolicy::Always {
+            self.file.sync_data()?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+/// Rebuild the pending events from the journal's lines, returning them in scheduling order
+/// together with the first unused ID.
+fn replay<E: DeserializeOwned>(lines: &[String]) -> ToolboxResult<(Vec<UnfiredEvent<E>>, u64)> {
+    let mut pending = BTreeMap::new();
+    let mut sequences = HashMap::new();
+    let mut next_id = 0;
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = match serde_json::from_str(line) {
+            Ok(record) => record,
+            Err(_) if index + 1 == lines.len() => break,
+            Err(err) => return Err(err.into()),
+        };
+        match record {
+            Record::Schedule {
+                id,
+                occurrence,
+                event,
+            } => {
+                next_id = next_id.max(id.0 + 1);
+                let sequence = *sequences.entry(id).or_insert(index);
+                pending.insert(
+                    sequence,
+                    UnfiredEvent {
+                        id,
+                        event,
+                        occurrence,
+                    },
+                );
+            }
+            Record::Replace { id, event } => {
+                if let Some(unfired) = sequences
+                    .get(&id)
+                    .and_then(|sequence| pending.get_mut(sequence))
+                {
+                    unfired.event = event;
+                }
+            }
+            Record::Fire {
+                id,
+                next: Some(next),
+            } => {
+                if let Some(unfired) = sequences
+                    .get(&id)
+                    .and_then(|sequence| pending.get_mut(sequence))
+                {
+                    unfired.event = next.event;
+                    unfired.occurrence = next.occurrence;
+                }
+            }
+            Record::Cancel { id } | Record::Fire { id, next: None } => {
+                if let Some(sequence) = sequences.remove(&id) {
+                    pending.remove(&sequence);
+                }
+            }
+        }
+    }
+    Ok((pending.into_values().collect(), next_id))
+}
+
+#[cfg(test)]
+mod persistence_tests {
+    use std::{
+        fs,
+        io::Write,
+        path::{Path, PathBuf},
+        sync::Arc,
+    };
+
+    use tokio::sync::{mpsc, RwLock};
+
+    use crate::scheduler::event::{Event, EventId, EventNotification};
+    use crate::scheduler::internal::{DiscreteEventOptions, SchedulerHandle, SchedulerOptions};
+    use crate::scheduler::persistence::{Journal, JournalOptions, RecoveryPolicy};
+    use crate::scheduler::standard_events::{CountedEvent, RecurringEventBuilder};
+    use crate::time::{Clock, ManualClock, SimClock, SimDuration, SimTime};
+
+    type TestEvent = CountedEvent<ManualClock, SimDuration>;
+    type Notifications = mpsc::UnboundedReceiver<EventNotification<ManualClock>>;
+
+    fn journal_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "scheduler-journal-{}-{}.jsonl",
+            std::process::id(),
+            name
+        ));
+        _ = fs::remove_file(&path);
+        path
+    }
+
+    fn counted(name: &str, start: u64, times: u64) -> TestEvent {
+        RecurringEventBuilder::new(name, SimTime::from_seconds(start), SimDuration::seconds(1))
+            .times(times)
+    }
+
+    fn start(
+        path: &Path,
+        now: SimTime,
+        recovery: RecoveryPolicy,
+    ) -> (
+        Arc<RwLock<ManualClock>>,
+        SchedulerHandle<ManualClock, TestEvent>,
+        Notifications,
+    ) {
+        let journal = Journal::open(JournalOptions {
+            compact_after: 2,
+            recovery,
+            ..JournalOptions::new(path)
+        })
+        .unwrap();
+        let clock = Arc::new(RwLock::new(ManualClock::new(now)));
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::with_journal(
+            Arc::clone(&clock),
+            sender,
+            SchedulerOptions::default(),
+            journal,
+        );
+        (clock, handle, receiver)
+    }
+
+    /// Run a scheduler that leaves "tick" due from 3s to 10s and "once" due at 20s.
+    async fn write_journal(path: &Path) {
+        _ = fs::remove_file(path);
+        let (clock, handle, mut notifications) = start(path, SimTime::zero(), RecoveryPolicy::Fire);
+        handle.schedule(counted("tick", 1, 10)).await.unwrap();
+        handle.schedule(counted("once", 20, 1)).await.unwrap();
+        let gone = handle.schedule(counted("gone", 4, 1)).await.unwrap();
+        assert_eq!(handle.cancel(gone).await.unwrap(), 1);
+        clock.write().await.advance_by(SimDuration::seconds(2));
+        for _ in 0..2 {
+            notifications.recv().await.unwrap();
+        }
+        handle.stop().await.unwrap();
+    }
+
+    async fn pending(handle: &SchedulerHandle<ManualClock, TestEvent>) -> Vec<(String, u64)> {
+        handle
+            .pending_events()
+            .await
+            .unwrap()
+            .iter()
+            .map(|pending| (pending.name().to_string(), pending.time().as_seconds()))
+            .collect()
+    }
+
+    fn expected(pending: &[(&str, u64)]) -> Vec<(String, u64)> {
+        pending
+            .iter()
+            .map(|(name, time)| (name.to_string(), *time))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn journal_recovers_pending_events_after_restart() {
+        let path = journal_path("recover");
+        write_journal(&path).await;
+        // A record cut short by a crash is ignored.
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"op\":\"sched")
+            .unwrap();
+
+        let journal: Journal<TestEvent> = Journal::open(JournalOptions::new(&path)).unwrap();
+        let recovered: Vec<_> = journal
+            .recovered()
+            .iter()
+            .map(|unfired| {
+                (
+                    unfired.id,
+                    unfired.occurrence,
+                    unfired.event.name().to_string(),
+                    unfired.event.execution_time().as_seconds(),
+                    unfired.event.remaining(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            recovered,
+            [
+                (EventId(0), 2, "tick".to_string(), 3, 8),
+                (EventId(1), 0, "once".to_string(), 20, 1),
+            ]
+        );
+        drop(journal);
+
+        let (clock, handle, mut notifications) =
+            start(&path, SimTime::zero(), RecoveryPolicy::Fire);
+        assert_eq!(
+            handle.schedule(counted("new", 30, 1)).await.unwrap(),
+            EventId(2)
+        );
+        clock.write().await.advance_to(SimTime::from_seconds(3));
+        let notification = notifications.recv().await.unwrap();
+        assert_eq!(notification.id(), EventId(0));
+        assert_eq!(notification.occurrence(), 2);
+        handle.stop().await.unwrap();
+        _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn recovery_policy_handles_events_missed_while_down() {
+        let path = journal_path("policies");
+        write_journal(&path).await;
+        let restart = SimTime::from_millis(5500);
+
+        let (_clock, handle, mut notifications) = start(&path, restart, RecoveryPolicy::Fire);
+        let mut fired = Vec::new();
+        for _ in 0..3 {
+            fired.push(notifications.recv().await.unwrap().time().as_seconds());
+        }
+        assert_eq!(fired, [3, 4, 5]);
+        handle.stop().await.unwrap();
+
+        write_journal(&path).await;
+        let (_clock, handle, _notifications) = start(&path, restart, RecoveryPolicy::Skip);
+        assert_eq!(
+            pending(&handle).await,
+            expected(&[("tick", 6), ("once", 20)])
+        );
+        handle.stop().await.unwrap();
+
+        write_journal(&path).await;
+        let (_clock, handle, _notifications) = start(&path, restart, RecoveryPolicy::Discard);
+        assert_eq!(pending(&handle).await, expected(&[("once", 20)]));
+        handle.stop().await.unwrap();
+        _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn intervals_beyond_microsecond_range_fail_to_journal() {
+        let interval = SimDuration::milliseconds(i64::MAX);
+        assert!(serde_json::to_vec(&interval).is_err());
+        let interval = SimDuration::microseconds(-1_500);
+        let json = serde_json::to_vec(&interval).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<SimDuration>(&json).unwrap(),
+            interval
+        );
+    }
+
+    #[tokio::test]
+    async fn acknowledged_changes_are_in_the_journal() {
+        let path = journal_path("acknowledged");
+        let (_clock, handle, _notifications) = start(&path, SimTime::zero(), RecoveryPolicy::Fire);
+        handle.schedule(counted("durable", 5, 1)).await.unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("durable"));
+        handle.stop().await.unwrap();
+        _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn discrete_scheduler_recovers_from_journal() {
+        let path = journal_path("discrete");
+        write_journal(&path).await;
+
+        let journal = Journal::open(JournalOptions::new(&path)).unwrap();
+        let clock = Arc::new(RwLock::new(ManualClock::new(SimTime::from_millis(5500))));
+        clock.write().await.pause();
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle: SchedulerHandle<ManualClock, TestEvent> =
+            SchedulerHandle::new_discrete_with_journal(
+                Arc::clone(&clock),
+                sender,
+                DiscreteEventOptions {
+                    stop_time: Some(SimTime::from_seconds(7)),
+                    ..Default::default()
+                },
+                journal,
+            );
+        clock.write().await.resume();
+
+        let mut fired = Vec::new();
+        while let Some(notification) = notifications.recv().await {
+            fired.push(notification.time().as_seconds());
+        }
+        assert_eq!(fired, [3, 4, 5, 6, 7]);
+        assert_eq!(clock.read().await.now(), SimTime::from_seconds(7));
+        handle.join().await;
+
+        let journal: Journal<TestEvent> = Journal::open(JournalOptions::new(&path)).unwrap();
+        let recovered: Vec<_> = journal
+            .recovered()
+            .iter()
+            .map(|unfired| unfired.event.execution_time().as_seconds())
+            .collect();
+        assert_eq!(recovered, [8, 20]);
+        _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,464 @@
+//! This module contains the queue of pending events owned by the scheduler actor.
+//!
+//! Events are ordered by execution time, then by [`Event::priority`], then by the order in
+//! which they were scheduled, so identical inputs always fire in an identical order regardless
+//! of how the event type implements [`Ord`]. The events are held by a [`PendingQueue`] backend
+//! selected with [`QueueBackend`].
+//!
+use std::{
+    cmp::Ordering,
+    cmp::Reverse,
+    collections::BinaryHeap,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+    sync::Arc,
+};
+
+use crate::time::Clock;
+
+use super::event::{Event, EventId, PendingEvent};
+use super::internal::QueueBackend;
+use super::timing_wheel::TimingWheel;
+
+/// An event waiting in the queue together with the ID it was scheduled under. Every occurrence
+/// of a recurring event keeps the ID and sequence number of the original schedule.
+pub(crate) struct ScheduledEvent<E> {
+    pub id: EventId,
+    pub event: E,
+    /// Name of the event, shared with its notifications so that firing does not copy it.
+    pub name: Arc<str>,
+    /// Number of earlier occurrences of the event.
+    pub occurrence: u64,
+    /// Position of the original schedule among every event scheduled on the queue.
+    pub sequence: u64,
+}
+
+/// Entry caching the ordering key of a scheduled event.
+pub(crate) struct QueueEntry<Time, E> {
+    pub time: Time,
+    pub priority: i32,
+    pub scheduled: ScheduledEvent<E>,
+}
+
+impl<Time: Ord, E> QueueEntry<Time, E> {
+    pub fn key(&self) -> (&Time, Reverse<i32>, u64) {
+        (&self.time, Reverse(self.priority), self.scheduled.sequence)
+    }
+}
+
+impl<Time: Ord, E> PartialEq for QueueEntry<Time, E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<Time: Ord, E> Eq for QueueEntry<Time, E> {}
+
+impl<Time: Ord, E> PartialOrd for QueueEntry<Time, E> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Time: Ord, E> Ord for QueueEntry<Time, E> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// Storage for the entries of an [`EventQueue`].
+pub(crate) trait PendingQueue<Time, E> {
+    fn insert(&mut self, entry: QueueEntry<Time, E>);
+    /// The earliest entry.
+    fn first(&self) -> Option<&QueueEntry<Time, E>>;
+    /// Remove the earliest entry.
+    fn pop_first(&mut self) -> Option<QueueEntry<Time, E>>;
+    /// The entry scheduled under `id`.
+    fn get(&self, id: EventId) -> Option<&QueueEntry<Time, E>>;
+    /// Remove the entry scheduled under `id`. Returns `true` if it was pending.
+    fn remove(&mut self, id: EventId) -> bool;
+    /// Remove every entry for which `keep` returns `false`.
+    fn retain(&mut self, keep: &mut dyn FnMut(&QueueEntry<Time, E>) -> bool);
+    /// Every entry, in no particular order.
+    fn entries(&self) -> Vec<&QueueEntry<Time, E>>;
+    /// Remove every entry, in no particular order.
+    fn take(&mut self) -> Vec<QueueEntry<Time, E>>;
+    fn len(&self) -> usize;
+}
+
+/// Binary heap of entries. Finding or removing an event by ID scans every entry.
+pub(crate) struct HeapQueue<Time, E> {
+    events: BinaryHeap<Reverse<QueueEntry<Time, E>>>,
+}
+
+impl<Time: Ord, E> HeapQueue<Time, E> {
+    /// Create an empty heap.
+    pub fn new() -> Self {
+        Self {
+            events: BinaryHeap::new(),
+        }
+    }
+}
+
+impl<Time: Ord, E> PendingQueue<Time, E> for HeapQueue<Time, E> {
+    fn insert(&mut self, entry: QueueEntry<Time, E>) {
+        self.events.push(Reverse(entry));
+    }
+
+    fn first(&self) -> Option<&QueueEntry<Time, E>> {
+        self.events.peek().map(|Reverse(entry)| entry)
+    }
+
+    fn pop_first(&mut self) -> Option<QueueEntry<Time, E>> {
+        self.events.pop().map(|Reverse(entry)| entry)
+    }
+
+    fn get(&self, id: EventId) -> Option<&QueueEntry<Time, E>> {
+        self.events
+            .iter()
+            .map(|Reverse(entry)| entry)
+            .find(|entry| entry.scheduled.id == id)
+    }
+
+    fn remove(&mut self, id: EventId) -> bool {
+        let before = self.events.len();
+        self.events
+            .retain(|Reverse(entry)| entry.scheduled.id != id);
+        self.events.len() != before
+    }
+
+    fn retain(&mut self, keep: &mut dyn FnMut(&QueueEntry<Time, E>) -> bool) {
+        self.events.retain(|Reverse(entry)| keep(entry));
+    }
+
+    fn entries(&self) -> Vec<&QueueEntry<Time, E>> {
+        self.events.iter().map(|Reverse(entry)| entry).collect()
+    }
+
+    fn take(&mut self) -> Vec<QueueEntry<Time, E>> {
+        std::mem::take(&mut self.events)
+            .into_iter()
+            .map(|Reverse(entry)| entry)
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.events.len()
+    }
+}
+
+enum Backend<Time, E> {
+    Heap(HeapQueue<Time, E>),
+    TimingWheel(Box<TimingWheel<Time, E>>),
+}
+
+/// Pending events, earliest first.
+pub(crate) struct EventQueue<T: Clock, E: Event<T>> {
+    backend: Backend<T::Time, E>,
+    next_sequence: u64,
+    _clock: PhantomData<fn() -> T>,
+}
+
+impl<T: Clock, E: Event<T>> EventQueue<T, E> {
+    /// Create an empty queue keeping its events in a heap.
+    pub fn new() -> Self {
+        Self {
+            backend: Backend::Heap(HeapQueue::new()),
+            next_sequence: 0,
+            _clock: PhantomData,
+        }
+    }
+
+    /// Move every pending event into `backend`. A timing wheel places times by their `tick`.
+    pub fn set_backend(&mut self, backend: QueueBackend, tick: fn(&T::Time) -> u64) {
+        let entries = self.events_mut().take();
+        self.backend = match backend {
+            QueueBackend::Heap => Backend::Heap(HeapQueue::new()),
+            QueueBackend::TimingWheel => Backend::TimingWheel(Box::new(TimingWheel::new(tick))),
+        };
+        for entry in entries {
+            self.events_mut().insert(entry);
+        }
+    }
+
+    fn events(&self) -> &dyn PendingQueue<T::Time, E> {
+        match &self.backend {
+            Backend::Heap(heap) => heap,
+            Backend::TimingWheel(wheel) => wheel.as_ref(),
+        }
+    }
+
+    fn events_mut(&mut self) -> &mut dyn PendingQueue<T::Time, E> {
+        match &mut self.backend {
+            Backend::Heap(heap) => heap,
+            Backend::TimingWheel(wheel) => wheel.as_mut(),
+        }
+    }
+
+    /// Queue the first occurrence of a newly scheduled event.
+    pub fn push(&mut self, id: EventId, event: E) {
+        self.push_occurrence(id, event, 0);
+    }
+
+    /// Queue a newly scheduled event that has already fired `occurrence` times, for example one
+    /// recovered from a journal.
+    pub fn push_occurrence(&mut self, id: EventId, event: E, occurrence: u64) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.push_scheduled(ScheduledEvent {
+            id,
+            name: event.name().into(),
+            event,
+            occurrence,
+            sequence,
+        });
+    }
+
+    /// Queue a later occurrence of an event.
+    pub fn push_scheduled(&mut self, scheduled: ScheduledEvent<E>) {
+        self.events_mut().insert(QueueEntry {
+            time: scheduled.event.execution_time(),
+            priority: scheduled.event.priority(),
+            scheduled,
+        });
+    }
+
+    /// Execution time of the earliest pending event.
+    pub fn next_time(&self) -> Option<T::Time> {
+        self.events().first().map(|entry| entry.time)
+    }
+
+    /// Remove the earliest pending event if it executes at or before `time`.
+    pub fn pop_due(&mut self, time: T::Time) -> Option<ScheduledEvent<E>> {
+        if self.next_time()? <= time {
+            self.events_mut().pop_first().map(|entry| entry.scheduled)
+        } else {
+            None
+        }
+    }
+
+    /// Remove the earliest pending event if it executes strictly before `time`.
+    pub fn pop_before(&mut self, time: T::Time) -> Option<ScheduledEvent<E>> {
+        if self.next_time()? < time {
+            self.events_mut().pop_first().map(|entry| entry.scheduled)
+        } else {
+            None
+        }
+    }
+
+    /// Remove the event scheduled under `id`. Returns `true` if it was pending.
+    pub fn cancel(&mut self, id: EventId) -> bool {
+        self.events_mut().remove(id)
+    }
+
+    /// Remove every event named `name`, returning the IDs of the events removed.
+    pub fn cancel_by_name(&mut self, name: &str) -> Vec<EventId> {
+        let mut removed = Vec::new();
+        self.events_mut().retain(&mut |entry| {
+            let keep = entry.scheduled.event.name() != name;
+            if !keep {
+                removed.push(entry.scheduled.id);
+            }
+            keep
+        });
+        removed
+    }
+
+    /// Replace the event scheduled under `id`, keeping its ID, occurrence count and sequence
+    /// number. Returns `true` if it was pending.
+    pub fn replace(&mut self, id: EventId, event: E) -> bool {
+        let Some((occurrence, sequence)) = self
+            .events()
+            .get(id)
+            .map(|entry| (entry.scheduled.occurrence, entry.scheduled.sequence))
+        else {
+            return false;
+        };
+        self.cancel(id);
+        self.push_scheduled(ScheduledEvent {
+            id,
+            name: event.name().into(),
+            event,
+            occurrence,
+            sequence,
+        });
+        true
+    }
+
+    /// The pending event scheduled under `id`.
+    pub fn event(&self, id: EventId) -> Option<&E> {
+        self.events().get(id).map(|entry| &entry.scheduled.event)
+    }
+
+    /// Execution time of the event scheduled under `id`.
+    pub fn time_of(&self, id: EventId) -> Option<T::Time> {
+        self.events().get(id).map(|entry| entry.time)
+    }
+
+    /// Move every pending event through `f`, which may return a different occurrence.
+    pub fn remap(&mut self, mut f: impl FnMut(ScheduledEvent<E>) -> ScheduledEvent<E>) {
+        for entry in self.events_mut().take() {
+            self.push_scheduled(f(entry.scheduled));
+        }
+    }
+
+    /// Pending events executing within `range`, earliest first.
+    pub fn pending(&self, range: &(Bound<T::Time>, Bound<T::Time>)) -> Vec<PendingEvent<T>> {
+        let mut pending: Vec<_> = self
+            .events()
+            .entries()
+            .into_iter()
+            .filter(|entry| range.contains(&entry.time))
+            .collect();
+        pending.sort();
+        pending
+            .into_iter()
+            .map(|entry| PendingEvent {
+                id: entry.scheduled.id,
+                name: entry.scheduled.event.name().to_string(),
+                time: entry.time,
+            })
+            .collect()
+    }
+
+    /// Every pending event in the order it was scheduled.
+    pub fn by_sequence(&self) -> Vec<&ScheduledEvent<E>> {
+        let mut scheduled: Vec<_> = self
+            .events()
+            .entries()
+            .into_iter()
+            .map(|entry| &entry.scheduled)
+            .collect();
+        scheduled.sort_by_key(|scheduled| scheduled.sequence);
+        scheduled
+    }
+
+    /// Number of pending events.
+    pub fn len(&self) -> usize {
+        self.events().len()
+    }
+
+    /// Remove every pending event, earliest first.
+    pub fn drain(&mut self) -> Vec<ScheduledEvent<E>> {
+        let mut drained = self.events_mut().take();
+        drained.sort();
+        drained.into_iter().map(|entry| entry.scheduled).collect()
+    }
+
+    /// Remove every pending event.
+    pub fn clear(&mut self) {
+        self.events_mut().take();
+    }
+}
+
+#[cfg(test)]
+mod queue_tests {
+    use crate::scheduler::event::{Event, EventId};
+    use crate::scheduler::internal::QueueBackend;
+    use crate::scheduler::queue::EventQueue;
+    use crate::scheduler::standard_events::OneShotEvent;
+    use crate::time::{ManualClock, SimTime, TickTime};
+
+    type Queue = EventQueue<ManualClock, OneShotEvent<ManualClock>>;
+
+    fn queue(backend: QueueBackend) -> Queue {
+        let mut queue = Queue::new();
+        queue.set_backend(backend, TickTime::tick);
+        queue
+    }
+
+    /// Deterministic pseudo-random numbers.
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 33) % bound
+        }
+
+        /// A time that is often within the same millisecond as others and sometimes years away.
+        fn time(&mut self) -> SimTime {
+            match self.next(4) {
+                0 => SimTime::from_micros(self.next(5_000)),
+                1 => SimTime::from_millis(self.next(100_000)),
+                2 => SimTime::from_seconds(self.next(100_000_000)),
+                _ => SimTime::from_millis(self.next(20) * 64),
+            }
+        }
+
+        fn event(&mut self) -> OneShotEvent<ManualClock> {
+            let name = ["a", "b", "c"][self.next(3) as usize];
+            OneShotEvent::new(name, self.time()).with_priority(self.next(3) as i32 - 1)
+        }
+    }
+
+    /// Apply the same random operations to `queue`, returning every event fired.
+    fn run(mut queue: Queue) -> Vec<(EventId, SimTime)> {
+        let mut random = Lcg(7);
+        let mut fired = Vec::new();
+        let mut next_id = 0;
+        for _ in 0..20_000 {
+            match random.next(10) {
+                0..=4 => {
+                    queue.push(EventId(next_id), random.event());
+                    next_id += 1;
+                }
+                5 => {
+                    queue.cancel(EventId(random.next(next_id + 1)));
+                }
+                6 => {
+                    queue.replace(EventId(random.next(next_id + 1)), random.event());
+                }
+                7 if random.next(50) == 0 => {
+                    queue.cancel_by_name("c");
+                }
+                _ => {
+                    let time = random.time();
+                    while let Some(scheduled) = queue.pop_due(time) {
+                        fired.push((scheduled.id, scheduled.event.execution_time()));
+                    }
+                }
+            }
+        }
+        assert_eq!(queue.len(), queue.by_sequence().len());
+        while let Some(scheduled) = queue.pop_due(SimTime::from_micros(u64::MAX)) {
+            fired.push((scheduled.id, scheduled.event.execution_time()));
+        }
+        assert_eq!(queue.len(), 0);
+        fired
+    }
+
+    #[test]
+    fn timing_wheel_fires_in_heap_order() {
+        let heap = run(queue(QueueBackend::Heap));
+        let wheel = run(queue(QueueBackend::TimingWheel));
+        assert!(heap.len() > 5_000);
+        assert_eq!(heap, wheel);
+    }
+
+    #[test]
+    fn changing_backend_keeps_pending_events() {
+        let fill = |queue: &mut Queue| {
+            let mut random = Lcg(3);
+            for id in 0..1_000 {
+                queue.push(EventId(id), random.event());
+            }
+        };
+        let fire = |mut queue: Queue| {
+            let mut fired = Vec::new();
+            while let Some(scheduled) = queue.pop_due(SimTime::from_micros(u64::MAX)) {
+                fired.push(scheduled.id);
+            }
+            fired
+        };
+        let (mut heap, mut moved) = (Queue::new(), Queue::new());
+        fill(&mut heap);
+        fill(&mut moved);
+        moved.set_backend(QueueBackend::TimingWheel, TickTime::tick);
+        assert_eq!(moved.len(), 1_000);
+        assert_eq!(fire(moved), fire(heap));
+    }
+}
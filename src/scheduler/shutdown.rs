@@ -0,0 +1,49 @@
+//! This module contains the types describing how a scheduler actor stops.
+//!
+//! [`SchedulerHandle::shutdown`](super::internal::SchedulerHandle::shutdown) stops the actor
+//! gracefully according to a [`ShutdownMode`] and returns a [`ShutdownReport`] with the events
+//! that never fired. Every handle can wait for the actor with
+//! [`SchedulerHandle::join`](super::internal::SchedulerHandle::join), which reports the
+//! [`ExitReason`].
+//!
+use super::event::UnfiredEvent;
+
+/// What happens to events due before a shutdown completes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownMode<Time> {
+    /// Stop at once without firing anything else.
+    Immediate,
+    /// Keep running, firing events as they come due on the clock, until no pending event
+    /// executes at or before the deadline.
+    FireUntil(Time),
+    /// Fire every event executing at or before the deadline at once, as if the clock had
+    /// already reached it, then stop. The clock is not moved.
+    FlushUntil(Time),
+}
+
+/// Why a scheduler actor stopped running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitReason {
+    /// Stopped with [`SchedulerHandle::stop`](super::internal::SchedulerHandle::stop), dropping
+    /// every pending event.
+    Stopped,
+    /// Shut down with
+    /// [`SchedulerHandle::shutdown`](super::internal::SchedulerHandle::shutdown).
+    Shutdown,
+    /// A discrete-event run finished because its queue emptied or it reached its stop time.
+    Completed,
+    /// The actor returned an error, for example because the notification receiver was dropped.
+    Failed(String),
+    /// The actor task panicked or was cancelled.
+    Aborted(String),
+}
+
+/// Outcome of a graceful shutdown.
+#[derive(Debug)]
+pub struct ShutdownReport<E> {
+    /// Events still queued when the actor stopped, earliest first.
+    pub pending: Vec<UnfiredEvent<E>>,
+    /// Why the actor stopped. [`ExitReason::Shutdown`] unless it had already stopped for
+    /// another reason.
+    pub reason: ExitReason,
+}
@@ -0,0 +1,841 @@
+//! This module contains ready-made [`Event`] implementations for common schedules.
+//!
+//! The events work with any [`Clock`] whose time can be moved by a duration, for example
+//! [`SimTime`](crate::time::SimTime) with [`SimDuration`](crate::time::SimDuration) or
+//! [`WallTime`](crate::time::WallTime) with [`TimeDuration`](crate::time::TimeDuration).
+//! Recurring events are built with [`RecurringEventBuilder`]:
+//!
+//! ```
+//! use sample::scheduler::standard_events::{CountedEvent, RecurringEventBuilder};
+//! use sample::time::{ManualClock, SimDuration, SimTime};
+//!
+//! let event: CountedEvent<ManualClock, SimDuration> =
+//!     RecurringEventBuilder::new("tick", SimTime::zero(), SimDuration::seconds(1))
+//!         .offset(SimDuration::milliseconds(500))
+//!         .times(3);
+//! ```
+//!
+use std::{cmp::Ordering, marker::PhantomData, ops::Add};
+
+use crate::time::Clock;
+
+use super::event::{Event, MisfirePolicy};
+
+/// Orders events by execution time, then by descending priority as the scheduler fires them, then
+/// by name.
+macro_rules! impl_event_ord {
+    ($event:ident < $($param:ident),+ >) => {
+        impl<$($param),+> PartialEq for $event<$($param),+>
+        where
+            T: Clock,
+        {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+
+        impl<$($param),+> Eq for $event<$($param),+> where T: Clock {}
+
+        impl<$($param),+> PartialOrd for $event<$($param),+>
+        where
+            T: Clock,
+        {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl<$($param),+> Ord for $event<$($param),+>
+        where
+            T: Clock,
+        {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.time
+                    .cmp(&other.time)
+                    .then_with(|| other.priority.cmp(&self.priority))
+                    .then_with(|| self.name.cmp(&other.name))
+            }
+        }
+    };
+}
+
+/// An event that fires once.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(bound(
+    serialize = "T::Time: serde::Serialize",
+    deserialize = "T::Time: serde::Deserialize<'de>"
+))]
+pub struct OneShotEvent<T: Clock> {
+    name: String,
+    time: T::Time,
+    priority: i32,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(skip)]
+    _clock: PhantomData<fn() -> T>,
+}
+
+impl<T: Clock> OneShotEvent<T> {
+    /// Create an event named `name` that fires at `time`.
+    pub fn new(name: impl Into<String>, time: T::Time) -> Self {
+        Self {
+            name: name.into(),
+            time,
+            priority: 0,
+            tags: Vec::new(),
+            _clock: PhantomData,
+        }
+    }
+
+    /// Fire before events with a lower priority that execute at the same time.
+    pub fn with_priority(self, priority: i32) -> Self {
+        Self { priority, ..self }
+    }
+
+    /// Add a tag that subscriptions can filter on.
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+}
+
+impl<T: Clock> Clone for OneShotEvent<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            time: self.time,
+            priority: self.priority,
+            tags: self.tags.clone(),
+            _clock: PhantomData,
+        }
+    }
+}
+
+impl_event_ord!(OneShotEvent<T>);
+
+impl<T: Clock> Event<T> for OneShotEvent<T> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execution_time(&self) -> T::Time {
+        self.time
+    }
+
+    /// One-shot events have no next occurrence, so the same event is returned.
+    fn next_time(&self) -> Self {
+        self.clone()
+    }
+
+    fn count(&self, _new_count: u64) -> Self {
+        self.clone()
+    }
+
+    fn with_execution_time(&self, time: T::Time) -> Option<Self> {
+        Some(Self {
+            time,
+            ..self.clone()
+        })
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+/// Builds the recurring event types, each firing every `interval` starting at `start` plus an
+/// optional offset.
+pub struct RecurringEventBuilder<T: Clock, D> {
+    name: String,
+    start: T::Time,
+    interval: D,
+    misfire_policy: Option<MisfirePolicy>,
+    priority: i32,
+    tags: Vec<String>,
+    _clock: PhantomData<fn() -> T>,
+}
+
+impl<T, D> RecurringEventBuilder<T, D>
+where
+    T: Clock,
+    T::Time: Add<D, Output = T::Time>,
+    D: Copy + Send + Sync,
+{
+    /// Start building an event named `name` that first fires at `start` and then every
+    /// `interval`.
+    pub fn new(name: impl Into<String>, start: T::Time, interval: D) -> Self {
+        Self {
+            name: name.into(),
+            start,
+            interval,
+            misfire_policy: None,
+            priority: 0,
+            tags: Vec::new(),
+            _clock: PhantomData,
+        }
+    }
+
+    /// Delay the first occurrence by `offset` after the start time.
+    pub fn offset(mut self, offset: D) -> Self {
+        self.start = self.start + offset;
+        self
+    }
+
+    /// Handle missed occurrences with `policy` instead of the clock's default.
+    pub fn misfire_policy(mut self, policy: MisfirePolicy) -> Self {
+        self.misfire_policy = Some(policy);
+        self
+    }
+
+    /// Fire before events with a lower priority that execute at the same time.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Add a tag that subscriptions can filter on.
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Repeat forever.
+    pub fn periodic(self) -> PeriodicEvent<T, D> {
+        PeriodicEvent {
+            name: self.name,
+            time: self.start,
+            start: self.start,
+            interval: self.interval,
+            misfire_policy: self.misfire_policy,
+            priority: self.priority,
+            tags: self.tags,
+            _clock: PhantomData,
+        }
+    }
+
+    /// Fire `times` occurrences in total. The first occurrence always fires, so zero behaves
+    /// like one.
+    pub fn times(self, times: u64) -> CountedEvent<T, D> {
+        CountedEvent {
+            name: self.name,
+            time: self.start,
+            start: self.start,
+            interval: self.interval,
+            remaining: times.max(1),
+            misfire_policy: self.misfire_policy,
+            priority: self.priority,
+            tags: self.tags,
+            _clock: PhantomData,
+        }
+    }
+
+    /// Repeat for every occurrence at or before `until`. The first occurrence always fires.
+    pub fn until(self, until: T::Time) -> UntilEvent<T, D> {
+        UntilEvent {
+            name: self.name,
+            time: self.start,
+            start: self.start,
+            interval: self.interval,
+            until,
+            misfire_policy: self.misfire_policy,
+            priority: self.priority,
+            tags: self.tags,
+            _clock: PhantomData,
+        }
+    }
+}
+
+/// The last occurrence before `time` of a schedule repeating every `interval` from `start`, or
+/// `None` if there is none. Times can only be moved forwards by a duration, so the occurrences
+/// are stepped through from `start`.
+fn previous_occurrence<Time, D>(start: Time, interval: D, time: Time) -> Option<Time>
+where
+    Time: Ord + Copy + Add<D, Output = Time>,
+    D: Copy,
+{
+    let mut previous = None;
+    let mut current = start;
+    while current < time {
+        previous = Some(current);
+        let next = current + interval;
+        if next <= current {
+            // Every occurrence of a schedule that does not move forwards is at `start`.
+            return None;
+        }
+        current = next;
+    }
+    previous
+}
+
+/// An event that fires every `interval` until cancelled.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(bound(
+    serialize = "T::Time: serde::Serialize, D: serde::Serialize",
+    deserialize = "T::Time: serde::Deserialize<'de>, D: serde::Deserialize<'de>"
+))]
+pub struct PeriodicEvent<T: Clock, D> {
+    name: String,
+    time: T::Time,
+    start: T::Time,
+    interval: D,
+    misfire_policy: Option<MisfirePolicy>,
+    priority: i32,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(skip)]
+    _clock: PhantomData<fn() -> T>,
+}
+
+impl<T: Clock, D: Copy> Clone for PeriodicEvent<T, D> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            time: self.time,
+            start: self.start,
+            interval: self.interval,
+            misfire_policy: self.misfire_policy,
+            priority: self.priority,
+            tags: self.tags.clone(),
+            _clock: PhantomData,
+        }
+    }
+}
+
+impl<T: Clock, D> PeriodicEvent<T, D> {
+    /// Time between occurrences.
+    pub fn interval(&self) -> &D {
+        &self.interval
+    }
+}
+
+impl_event_ord!(PeriodicEvent<T, D>);
+
+impl<T, D> Event<T> for PeriodicEvent<T, D>
+where
+    T: Clock,
+    T::Time: Add<D, Output = T::Time>,
+    D: Copy + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execution_time(&self) -> T::Time {
+        self.time
+    }
+
+    fn next_time(&self) -> Self {
+        Self {
+            time: self.time + self.interval,
+            ..self.clone()
+        }
+    }
+
+    /// Periodic events repeat until cancelled, so the count is ignored. Use [`CountedEvent`] to
+    /// limit the number of occurrences.
+    fn count(&self, _new_count: u64) -> Self {
+        self.clone()
+    }
+
+    /// The previous occurrence, or `None` for the first occurrence.
+    fn previous_time(&self) -> Option<Self> {
+        previous_occurrence(self.start, self.interval, self.time).map(|previous| Self {
+            time: previous,
+            ..self.clone()
+        })
+    }
+
+    fn with_execution_time(&self, time: T::Time) -> Option<Self> {
+        Some(Self {
+            time,
+            ..self.clone()
+        })
+    }
+
+    fn misfire_policy(&self) -> Option<MisfirePolicy> {
+        self.misfire_policy
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+/// An event that fires every `interval` a fixed number of times.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(bound(
+    serialize = "T::Time: serde::Serialize, D: serde::Serialize",
+    deserialize = "T::Time: serde::Deserialize<'de>, D: serde::Deserialize<'de>"
+))]
+pub struct CountedEvent<T: Clock, D> {
+    name: String,
+    time: T::Time,
+    start: T::Time,
+    interval: D,
+    remaining: u64,
+    misfire_policy: Option<MisfirePolicy>,
+    priority: i32,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(skip)]
+    _clock: PhantomData<fn() -> T>,
+}
+
+impl<T: Clock, D: Copy> Clone for CountedEvent<T, D> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            time: self.time,
+            start: self.start,
+            interval: self.interval,
+            remaining: self.remaining,
+            misfire_policy: self.misfire_policy,
+            priority: self.priority,
+            tags: self.tags.clone(),
+            _clock: PhantomData,
+        }
+    }
+}
+
+impl<T: Clock, D> CountedEvent<T, D> {
+    /// Number of occurrences left to fire, including this one.
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+}
+
+impl_event_ord!(CountedEvent<T, D>);
+
+impl<T, D> Event<T> for CountedEvent<T, D>
+where
+    T: Clock,
+    T::Time: Add<D, Output = T::Time>,
+    D: Copy + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execution_time(&self) -> T::Time {
+        self.time
+    }
+
+    /// The next occurrence, or the same event once this is the last occurrence.
+    fn next_time(&self) -> Self {
+        if self.remaining <= 1 {
+            return self.clone();
+        }
+        Self {
+            time: self.time + self.interval,
+            remaining: self.remaining - 1,
+            ..self.clone()
+        }
+    }
+
+    /// Set the number of occurrences left to fire, including this one.
+    fn count(&self, new_count: u64) -> Self {
+        Self {
+            remaining: new_count,
+            ..self.clone()
+        }
+    }
+
+    fn remaining(&self) -> Option<u64> {
+        Some(self.remaining)
+    }
+
+    /// The previous occurrence with one more occurrence left to fire, or `None` for the first
+    /// occurrence.
+    fn previous_time(&self) -> Option<Self> {
+        previous_occurrence(self.start, self.interval, self.time).map(|previous| Self {
+            time: previous,
+            remaining: self.remaining + 1,
+            ..self.clone()
+        })
+    }
+
+    fn with_execution_time(&self, time: T::Time) -> Option<Self> {
+        Some(Self {
+            time,
+            ..self.clone()
+        })
+    }
+
+    fn misfire_policy(&self) -> Option<MisfirePolicy> {
+        self.misfire_policy
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+/// An event that fires every `interval` until a given time.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(bound(
+    serialize = "T::Time: serde::Serialize, D: serde::Serialize",
+    deserialize = "T::Time: serde::Deserialize<'de>, D: serde::Deserialize<'de>"
+))]
+pub struct UntilEvent<T: Clock, D> {
+    name: String,
+    time: T::Time,
+    start: T::Time,
+    interval: D,
+    until: T::Time,
+    misfire_policy: Option<MisfirePolicy>,
+    priority: i32,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(skip)]
+    _clock: PhantomData<fn() -> T>,
+}
+
+impl<T: Clock, D: Copy> Clone for UntilEvent<T, D> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            time: self.time,
+            start: self.start,
+            interval: self.interval,
+            until: self.until,
+            misfire_policy: self.misfire_policy,
+            priority: self.priority,
+            tags: self.tags.clone(),
+            _clock: PhantomData,
+        }
+    }
+}
+
+impl<T: Clock, D> UntilEvent<T, D> {
+    /// Time of the last possible occurrence.
+    pub fn until(&self) -> T::Time {
+        self.until
+    }
+}
+
+impl_event_ord!(UntilEvent<T, D>);
+
+impl<T, D> Event<T> for UntilEvent<T, D>
+where
+    T: Clock,
+    T::Time: Add<D, Output = T::Time>,
+    D: Copy + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execution_time(&self) -> T::Time {
+        self.time
+    }
+
+    /// The next occurrence, or the same event once the next occurrence would be after `until`.
+    fn next_time(&self) -> Self {
+        let next = self.time + self.interval;
+        if next > self.until {
+            return self.clone();
+        }
+        Self {
+            time: next,
+            ..self.clone()
+        }
+    }
+
+    fn count(&self, _new_count: u64) -> Self {
+        self.clone()
+    }
+
+    /// The previous occurrence, or `None` for the first occurrence.
+    fn previous_time(&self) -> Option<Self> {
+        previous_occurrence(self.start, self.interval, self.time).map(|previous| Self {
+            time: previous,
+            ..self.clone()
+        })
+    }
+
+    fn with_execution_time(&self, time: T::Time) -> Option<Self> {
+        Some(Self {
+            time,
+            ..self.clone()
+        })
+    }
+
+    fn misfire_policy(&self) -> Option<MisfirePolicy> {
+        self.misfire_policy
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn tags(&self) -> &[String] {
+        &self.tags
+    }
+}
+
+#[cfg(test)]
+mod standard_events_tests {
+    use std::sync::Arc;
+
+    use tokio::sync::{mpsc, RwLock};
+
+    use crate::scheduler::event::EventId;
+    use crate::scheduler::event::{Event, MisfirePolicy};
+    use crate::scheduler::internal::SchedulerHandle;
+    use crate::scheduler::standard_events::{
+        CountedEvent, OneShotEvent, PeriodicEvent, RecurringEventBuilder, UntilEvent,
+    };
+    use crate::time::{
+        ManualClock, SimClock, SimDuration, SimTime, SystemWallClock, TimeDuration, WallTime,
+    };
+
+    fn occurrences<E: Event<ManualClock>>(event: E, limit: usize) -> Vec<u64> {
+        let mut times = vec![event.execution_time().as_seconds()];
+        let mut current = event;
+        while times.len() < limit {
+            let next = current.next_time();
+            if next.execution_time() == current.execution_time() {
+                break;
+            }
+            times.push(next.execution_time().as_seconds());
+            current = next;
+        }
+        times
+    }
+
+    fn every_two_seconds() -> RecurringEventBuilder<ManualClock, SimDuration> {
+        RecurringEventBuilder::new("tick", SimTime::from_seconds(10), SimDuration::seconds(2))
+    }
+
+    #[test]
+    pub fn recurring_events_follow_their_limits() {
+        let one_shot: OneShotEvent<ManualClock> = OneShotEvent::new("once", SimTime::zero());
+        assert_eq!(occurrences(one_shot, 10), [0]);
+
+        let periodic: PeriodicEvent<_, _> = every_two_seconds()
+            .offset(SimDuration::seconds(1))
+            .periodic();
+        assert_eq!(occurrences(periodic, 4), [11, 13, 15, 17]);
+
+        let counted: CountedEvent<_, _> = every_two_seconds().times(3);
+        assert_eq!(occurrences(counted.clone(), 10), [10, 12, 14]);
+        assert_eq!(occurrences(counted.count(1), 10), [10]);
+
+        let until: UntilEvent<_, _> = every_two_seconds().until(SimTime::from_seconds(15));
+        assert_eq!(occurrences(until, 10), [10, 12, 14]);
+    }
+
+    #[test]
+    pub fn events_order_like_the_scheduler_fires_them() {
+        let low: OneShotEvent<ManualClock> = OneShotEvent::new("a", SimTime::from_seconds(1));
+        let high = OneShotEvent::new("b", SimTime::from_seconds(1)).with_priority(1);
+        let later = OneShotEvent::new("a", SimTime::from_seconds(2)).with_priority(5);
+        assert!(high < low);
+        assert!(low < later);
+        assert!(low != OneShotEvent::new("a", SimTime::from_seconds(1)).with_priority(1));
+    }
+
+    #[test]
+    pub fn periodic_event_works_with_wall_time() {
+        let start = WallTime::from_timestamp_millis(0).unwrap();
+        let event: PeriodicEvent<SystemWallClock, TimeDuration> =
+            RecurringEventBuilder::new("wall", start, TimeDuration::milliseconds(250)).periodic();
+        assert_eq!(
+            Event::<SystemWallClock>::next_time(&event)
+                .execution_time()
+                .timestamp_millis(),
+            250
+        );
+    }
+
+    #[test]
+    pub fn periodic_event_works_with_time_durations_on_sim_time() {
+        let event: PeriodicEvent<ManualClock, TimeDuration> =
+            RecurringEventBuilder::new("tick", SimTime::zero(), TimeDuration::milliseconds(2000))
+                .periodic();
+        let third = event.next_time().next_time();
+        assert_eq!(occurrences(event.clone(), 3), [0, 2, 4]);
+        assert_eq!(
+            third.previous_time().map(|event| event.execution_time()),
+            Some(SimTime::from_seconds(2))
+        );
+        assert!(event.previous_time().is_none());
+    }
+
+    async fn fire_late(policy: MisfirePolicy) -> (Vec<(u64, u64, u64)>, SimTime) {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::new(Arc::clone(&clock), sender);
+        let id = handle
+            .schedule(
+                RecurringEventBuilder::new(
+                    "tick",
+                    SimTime::from_seconds(1),
+                    SimDuration::seconds(1),
+                )
+                .misfire_policy(policy)
+                .periodic(),
+            )
+            .await
+            .unwrap();
+
+        clock.write().await.advance_to(SimTime::from_millis(4_500));
+        let next = loop {
+            if let Some(next) = handle.scheduled_time(id).await.unwrap() {
+                if next > SimTime::from_millis(4_500) {
+                    break next;
+                }
+            }
+        };
+        let mut fired = Vec::new();
+        while let Ok(notification) = notifications.try_recv() {
+            fired.push((
+                notification.time().as_seconds(),
+                notification.occurrence(),
+                notification.missed(),
+            ));
+        }
+        (fired, next)
+    }
+
+    #[tokio::test]
+    async fn misfire_policies_handle_missed_occurrences() {
+        assert_eq!(
+            fire_late(MisfirePolicy::FireAll).await,
+            (
+                vec![(1, 0, 0), (2, 1, 0), (3, 2, 0), (4, 3, 0)],
+                SimTime::from_seconds(5)
+            )
+        );
+        assert_eq!(
+            fire_late(MisfirePolicy::Coalesce).await,
+            (vec![(4, 3, 3)], SimTime::from_seconds(5))
+        );
+        assert_eq!(
+            fire_late(MisfirePolicy::Skip).await,
+            (vec![(1, 0, 0)], SimTime::from_seconds(5))
+        );
+        assert_eq!(
+            fire_late(MisfirePolicy::Delay).await,
+            (vec![(1, 0, 0)], SimTime::from_millis(5_500))
+        );
+    }
+
+    #[tokio::test]
+    async fn scheduler_fires_counted_event_until_exhausted() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::new(Arc::clone(&clock), sender);
+        handle
+            .schedule(
+                RecurringEventBuilder::new(
+                    "tick",
+                    SimTime::from_seconds(1),
+                    SimDuration::seconds(1),
+                )
+                .times(3),
+            )
+            .await
+            .unwrap();
+
+        clock.write().await.advance_by(SimDuration::seconds(10));
+        let mut fired = Vec::new();
+        for _ in 0..3 {
+            fired.push(notifications.recv().await.unwrap().time().as_seconds());
+        }
+        assert_eq!(fired, [1, 2, 3]);
+        assert_eq!(handle.pending_count().await.unwrap(), 0);
+    }
+
+    async fn wait_for_time(
+        handle: &SchedulerHandle<ManualClock, impl Event<ManualClock> + 'static>,
+        id: EventId,
+        time: SimTime,
+    ) {
+        let moved = async {
+            while handle.scheduled_time(id).await.unwrap() != Some(time) {
+                tokio::task::yield_now().await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(1), moved)
+            .await
+            .expect("event should move to the expected time");
+    }
+
+    #[tokio::test]
+    async fn backward_seek_rearms_counted_event_with_its_remaining_count() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::new(Arc::clone(&clock), sender);
+        let id = handle
+            .schedule(
+                RecurringEventBuilder::new(
+                    "tick",
+                    SimTime::from_seconds(5),
+                    SimDuration::seconds(5),
+                )
+                .times(5),
+            )
+            .await
+            .unwrap();
+        clock.write().await.advance_to(SimTime::from_seconds(15));
+        for _ in 0..3 {
+            notifications.recv().await.unwrap();
+        }
+
+        clock.write().await.seek(SimTime::from_seconds(7));
+        wait_for_time(&handle, id, SimTime::from_seconds(10)).await;
+        clock.write().await.advance_to(SimTime::from_seconds(100));
+        let mut fired = Vec::new();
+        for _ in 0..4 {
+            let notification = notifications.recv().await.unwrap();
+            fired.push((
+                notification.time().as_seconds(),
+                notification.occurrence(),
+                notification.is_final(),
+            ));
+        }
+        assert_eq!(
+            fired,
+            [
+                (10, 1, false),
+                (15, 2, false),
+                (20, 3, false),
+                (25, 4, true)
+            ]
+        );
+        assert_eq!(handle.pending_count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn backward_seek_never_rearms_before_first_occurrence() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::new(Arc::clone(&clock), sender);
+        let periodic: PeriodicEvent<_, _> =
+            RecurringEventBuilder::new("tick", SimTime::from_seconds(20), SimDuration::seconds(5))
+                .periodic();
+        let id = handle.schedule(periodic).await.unwrap();
+        clock.write().await.advance_to(SimTime::from_seconds(30));
+        for _ in 0..3 {
+            notifications.recv().await.unwrap();
+        }
+
+        clock.write().await.seek(SimTime::from_seconds(27));
+        wait_for_time(&handle, id, SimTime::from_seconds(30)).await;
+        clock.write().await.seek(SimTime::zero());
+        wait_for_time(&handle, id, SimTime::from_seconds(20)).await;
+    }
+}
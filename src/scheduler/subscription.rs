@@ -0,0 +1,290 @@
+//! This module contains the filtered subscriptions that let any number of components receive
+//! scheduler notifications.
+//!
+//! Components subscribe at runtime through
+//! [`SchedulerHandle::subscribe`](super::internal::SchedulerHandle::subscribe) with a
+//! [`NotificationFilter`] and receive only the notifications it matches. A subscription ends
+//! when its receiver is dropped.
+//!
+use std::sync::Arc;
+
+use crate::time::Clock;
+
+use super::event::EventNotification;
+use super::internal::DrainTracker;
+use super::notification::NotificationSender;
+
+/// Predicate deciding whether a subscriber receives a notification.
+pub type NotificationPredicate<T, P = ()> =
+    Arc<dyn Fn(&EventNotification<T, P>) -> bool + Send + Sync>;
+
+/// Selects the notifications delivered to a subscriber.
+pub enum NotificationFilter<T: Clock, P = ()> {
+    /// Every notification.
+    All,
+    /// Notifications of events with exactly this name.
+    Name(String),
+    /// Notifications of events whose name starts with this prefix.
+    Prefix(String),
+    /// Notifications of events whose name matches this pattern, where `*` matches any sequence
+    /// of characters and `?` matches a single character.
+    Glob(String),
+    /// Notifications of events carrying this [tag](super::event::Event::tags).
+    Tag(String),
+    /// Notifications for which the predicate returns `true`.
+    Predicate(NotificationPredicate<T, P>),
+}
+
+impl<T: Clock, P> NotificationFilter<T, P> {
+    /// Match notifications for which `predicate` returns `true`.
+    pub fn predicate(
+        predicate: impl Fn(&EventNotification<T, P>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self::Predicate(Arc::new(predicate))
+    }
+
+    /// Returns `true` if `notification` should be delivered.
+    pub fn matches(&self, notification: &EventNotification<T, P>) -> bool {
+        match self {
+            NotificationFilter::All => true,
+            NotificationFilter::Name(name) => notification.name() == name,
+            NotificationFilter::Prefix(prefix) => notification.name.starts_with(prefix.as_str()),
+            NotificationFilter::Glob(pattern) => glob_matches(pattern, &notification.name),
+            NotificationFilter::Tag(tag) => notification.tags.contains(tag),
+            NotificationFilter::Predicate(predicate) => predicate(notification),
+        }
+    }
+}
+
+impl<T: Clock, P> Clone for NotificationFilter<T, P> {
+    fn clone(&self) -> Self {
+        match self {
+            NotificationFilter::All => NotificationFilter::All,
+            NotificationFilter::Name(name) => NotificationFilter::Name(name.clone()),
+            NotificationFilter::Prefix(prefix) => NotificationFilter::Prefix(prefix.clone()),
+            NotificationFilter::Glob(pattern) => NotificationFilter::Glob(pattern.clone()),
+            NotificationFilter::Tag(tag) => NotificationFilter::Tag(tag.clone()),
+            NotificationFilter::Predicate(predicate) => {
+                NotificationFilter::Predicate(Arc::clone(predicate))
+            }
+        }
+    }
+}
+
+/// Returns `true` if `name` matches `pattern`, where `*` matches any sequence of characters and
+/// `?` matches a single character.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` and the name position it currently matches up to.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    // Let the last `*` swallow one more character and retry.
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, n));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+struct Subscriber<T: Clock, P> {
+    filter: NotificationFilter<T, P>,
+    sender: NotificationSender<T, P>,
+    /// Whether deliveries count towards the discrete-event drain.
+    drain: bool,
+}
+
+/// Subscribers owned by the scheduler actor.
+pub(crate) struct Subscriptions<T: Clock, P> {
+    subscribers: Vec<Subscriber<T, P>>,
+}
+
+impl<T: Clock, P: Clone> Subscriptions<T, P> {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Vec::new(),
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        filter: NotificationFilter<T, P>,
+        sender: NotificationSender<T, P>,
+        drain: bool,
+    ) {
+        self.subscribers
+            .retain(|subscriber| !subscriber.sender.is_closed());
+        self.subscribers.push(Subscriber {
+            filter,
+            sender,
+            drain,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    /// Deliver `notification` to every matching subscriber, removing subscribers whose receiver
+    /// has been dropped. Every delivery to a subscriber taking part in the drain that grows its
+    /// queue of waiting notifications is counted in `drain` as it happens.
+    pub async fn publish(
+        &mut self,
+        notification: &EventNotification<T, P>,
+        drain: Option<&DrainTracker>,
+    ) {
+        let mut closed = false;
+        for subscriber in self
+            .subscribers
+            .iter()
+            .filter(|subscriber| subscriber.filter.matches(notification))
+        {
+            match subscriber.sender.send(notification.clone()).await {
+                Ok(true) if subscriber.drain => {
+                    if let Some(drain) = drain {
+                        drain.sent();
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => closed = true,
+            }
+        }
+        if closed {
+            self.subscribers
+                .retain(|subscriber| !subscriber.sender.is_closed());
+        }
+    }
+}
+
+#[cfg(test)]
+mod subscription_tests {
+    use std::sync::Arc;
+
+    use tokio::sync::{mpsc, RwLock};
+
+    use crate::scheduler::event::{EventId, EventNotification};
+    use crate::scheduler::internal::SchedulerHandle;
+    use crate::scheduler::standard_events::OneShotEvent;
+    use crate::scheduler::subscription::{glob_matches, NotificationFilter};
+    use crate::time::{ManualClock, SimDuration, SimTime};
+
+    fn notification(name: &str, tags: &[&str]) -> EventNotification<ManualClock> {
+        EventNotification {
+            id: EventId(0),
+            name: name.into(),
+            time: SimTime::zero(),
+            occurrence: 0,
+            missed: 0,
+            is_final: true,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            payload: (),
+        }
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_matches("sensor.*", "sensor.temperature"));
+        assert!(glob_matches("sensor.*", "sensor."));
+        assert!(glob_matches("*.temp?", "room.temp1"));
+        assert!(glob_matches("a*b*c", "axxbyybzc"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("sensor.*", "sensors.temperature"));
+        assert!(!glob_matches("a*b", "axxbc"));
+        assert!(!glob_matches("?", ""));
+    }
+
+    #[test]
+    fn filters_match_notifications() {
+        let tick = notification("sensor.tick", &["sensors", "fast"]);
+        assert!(NotificationFilter::All.matches(&tick));
+        assert!(NotificationFilter::Name("sensor.tick".to_string()).matches(&tick));
+        assert!(!NotificationFilter::Name("sensor".to_string()).matches(&tick));
+        assert!(NotificationFilter::Prefix("sensor.".to_string()).matches(&tick));
+        assert!(!NotificationFilter::Prefix("tick".to_string()).matches(&tick));
+        assert!(NotificationFilter::Glob("*.tick".to_string()).matches(&tick));
+        assert!(NotificationFilter::Tag("fast".to_string()).matches(&tick));
+        assert!(!NotificationFilter::Tag("slow".to_string()).matches(&tick));
+        assert!(
+            NotificationFilter::predicate(|n: &EventNotification<ManualClock>| n.is_final())
+                .matches(&tick)
+        );
+    }
+
+    fn names(
+        receiver: &mut mpsc::UnboundedReceiver<EventNotification<ManualClock>>,
+    ) -> Vec<String> {
+        let mut names = Vec::new();
+        while let Ok(notification) = receiver.try_recv() {
+            names.push(notification.name().to_string());
+        }
+        names
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_matching_notifications_until_dropped() {
+        let clock = Arc::new(RwLock::new(ManualClock::default()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle: SchedulerHandle<ManualClock, OneShotEvent<ManualClock>> =
+            SchedulerHandle::new(Arc::clone(&clock), sender);
+
+        let mut by_name = handle
+            .subscribe(NotificationFilter::Name("door".to_string()))
+            .await
+            .unwrap();
+        let mut by_prefix = handle
+            .subscribe(NotificationFilter::Prefix("sensor.".to_string()))
+            .await
+            .unwrap();
+        let mut by_tag = handle
+            .subscribe(NotificationFilter::Tag("fast".to_string()))
+            .await
+            .unwrap();
+        let by_predicate = handle
+            .subscribe(NotificationFilter::predicate(|notification| {
+                notification.time() >= SimTime::from_seconds(3)
+            }))
+            .await
+            .unwrap();
+        drop(by_predicate);
+
+        handle
+            .schedule(OneShotEvent::new("door", SimTime::from_seconds(1)))
+            .await
+            .unwrap();
+        handle
+            .schedule(OneShotEvent::new("sensor.speed", SimTime::from_seconds(2)).with_tag("fast"))
+            .await
+            .unwrap();
+        handle
+            .schedule(OneShotEvent::new("sensor.level", SimTime::from_seconds(3)))
+            .await
+            .unwrap();
+        clock.write().await.advance_by(SimDuration::seconds(3));
+
+        for _ in 0..3 {
+            notifications.recv().await.unwrap();
+        }
+        // Subscribers are notified after the scheduler's own sender, but before the next command
+        // is applied. The predicate matched "sensor.level", which removed its dropped subscriber.
+        assert_eq!(handle.subscriber_count().await.unwrap(), 3);
+        assert_eq!(names(&mut by_name), ["door"]);
+        assert_eq!(names(&mut by_prefix), ["sensor.speed", "sensor.level"]);
+        assert_eq!(names(&mut by_tag), ["sensor.speed"]);
+    }
+}
@@ -0,0 +1,265 @@
+//! This module contains a hierarchical timing wheel holding the pending events of an
+//! [`EventQueue`](super::queue::EventQueue).
+//!
+//! Events are sorted into slots by the tick of their execution time, usually whole milliseconds
+//! given by [`TickTime`](crate::time::TickTime). Each level has [`SLOTS`] slots, each 64 times
+//! wider than the slots of the level below, and enough levels cover every `u64` tick. Inserting
+//! and cancelling an event take amortized constant time, as an event moves down at most once
+//! per level as its time approaches.
+//! Events in the current millisecond are kept in a small heap so they leave the wheel in exactly
+//! the order of the heap queue.
+//!
+use std::{cmp::Reverse, collections::BinaryHeap, collections::HashMap};
+
+use super::event::EventId;
+use super::queue::{PendingQueue, QueueEntry};
+
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+/// Enough levels of [`SLOT_BITS`] bits to cover a `u64` tick.
+const LEVELS: usize = 11;
+
+#[derive(Clone, Copy)]
+enum Place {
+    /// In the heap of due entries.
+    Due,
+    Slot {
+        level: usize,
+        slot: usize,
+    },
+}
+
+struct Node<Time, E> {
+    entry: QueueEntry<Time, E>,
+    tick: u64,
+    /// Tells this node apart from earlier nodes stored at the same index.
+    stamp: u64,
+    place: Place,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// Ordering key of a due entry, followed by its stamp and index.
+type DueKey<Time> = Reverse<(Time, Reverse<i32>, u64, u64, usize)>;
+
+/// Hierarchical timing wheel of entries.
+pub(crate) struct TimingWheel<Time, E> {
+    nodes: Vec<Option<Node<Time, E>>>,
+    free: Vec<usize>,
+    ids: HashMap<EventId, usize>,
+    /// First node of every slot, by level.
+    slots: Vec<[Option<usize>; SLOTS]>,
+    /// Bit set of the non-empty slots of every level.
+    occupied: [u64; LEVELS],
+    /// Every entry in a slot executes after this tick and every due entry at or before it.
+    elapsed: u64,
+    /// Due entries, earliest first. Entries removed by ID are left behind and skipped. The heap
+    /// is only empty when the wheel is.
+    due: BinaryHeap<DueKey<Time>>,
+    next_stamp: u64,
+    /// Tick of a time.
+    tick: fn(&Time) -> u64,
+}
+
+impl<Time: Ord + Copy, E> TimingWheel<Time, E> {
+    /// Create an empty wheel placing times by their `tick`.
+    pub fn new(tick: fn(&Time) -> u64) -> Self {
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            ids: HashMap::new(),
+            slots: vec![[None; SLOTS]; LEVELS],
+            occupied: [0; LEVELS],
+            elapsed: 0,
+            due: BinaryHeap::new(),
+            next_stamp: 0,
+            tick,
+        }
+    }
+
+    fn node(&self, index: usize) -> &Node<Time, E> {
+        self.nodes[index]
+            .as_ref()
+            .expect("timing wheel node missing")
+    }
+
+    fn node_mut(&mut self, index: usize) -> &mut Node<Time, E> {
+        self.nodes[index]
+            .as_mut()
+            .expect("timing wheel node missing")
+    }
+
+    /// Put the node at `index` in the due heap or the slot its tick falls in.
+    fn place(&mut self, index: usize) {
+        let elapsed = self.elapsed;
+        let node = self.node_mut(index);
+        let tick = node.tick;
+        if tick <= elapsed {
+            node.place = Place::Due;
+            let key = Reverse((
+                node.entry.time,
+                Reverse(node.entry.priority),
+                node.entry.scheduled.sequence,
+                node.stamp,
+                index,
+            ));
+            self.due.push(key);
+            return;
+        }
+        // The level is set by the highest bit in which the tick differs from the elapsed tick.
+        let significant = 63 - ((elapsed ^ tick) | (SLOTS as u64 - 1)).leading_zeros();
+        let level = (significant / SLOT_BITS) as usize;
+        let slot = ((tick >> (SLOT_BITS * level as u32)) as usize) & (SLOTS - 1);
+        let head = self.slots[level][slot].replace(index);
+        let node = self.node_mut(index);
+        node.place = Place::Slot { level, slot };
+        node.prev = None;
+        node.next = head;
+        if let Some(head) = head {
+            self.node_mut(head).prev = Some(index);
+        }
+        self.occupied[level] |= 1 << slot;
+    }
+
+    /// Take the node at `index` out of its slot.
+    fn unlink(&mut self, index: usize) {
+        let node = self.node(index);
+        let Place::Slot { level, slot } = node.place else {
+            return;
+        };
+        let (prev, next) = (node.prev, node.next);
+        match prev {
+            Some(prev) => self.node_mut(prev).next = next,
+            None => self.slots[level][slot] = next,
+        }
+        if let Some(next) = next {
+            self.node_mut(next).prev = prev;
+        }
+        if self.slots[level][slot].is_none() {
+            self.occupied[level] &= !(1 << slot);
+        }
+    }
+
+    /// Remove the node at `index`, leaving any due key behind.
+    fn remove_index(&mut self, index: usize) -> QueueEntry<Time, E> {
+        self.unlink(index);
+        let node = self.nodes[index].take().expect("timing wheel node missing");
+        self.free.push(index);
+        if self.ids.get(&node.entry.scheduled.id) == Some(&index) {
+            self.ids.remove(&node.entry.scheduled.id);
+        }
+        node.entry
+    }
+
+    fn is_live(&self, stamp: u64, index: usize) -> bool {
+        matches!(&self.nodes[index], Some(node) if node.stamp == stamp)
+    }
+
+    /// Drop removed entries from the top of the due heap and refill it from the wheel once empty.
+    fn settle(&mut self) {
+        loop {
+            while let Some(Reverse((.., stamp, index))) = self.due.peek() {
+                if self.is_live(*stamp, *index) {
+                    return;
+                }
+                self.due.pop();
+            }
+            let Some(level) = self.occupied.iter().position(|occupied| *occupied != 0) else {
+                return;
+            };
+            // Every entry in the wheel is after the elapsed tick, so the lowest slot of the lowest
+            // level holds the earliest entries. Move to the start of that slot and place them again.
+            let slot = self.occupied[level].trailing_zeros() as usize;
+            let shift = SLOT_BITS * level as u32;
+            let level_mask = (1u64 << shift << SLOT_BITS).wrapping_sub(1);
+            self.elapsed = (self.elapsed & !level_mask) | ((slot as u64) << shift);
+            self.occupied[level] &= !(1 << slot);
+            let mut next = self.slots[level][slot].take();
+            while let Some(index) = next {
+                next = self.node(index).next;
+                self.place(index);
+            }
+        }
+    }
+}
+
+impl<Time: Ord + Copy, E> PendingQueue<Time, E> for TimingWheel<Time, E> {
+    fn insert(&mut self, entry: QueueEntry<Time, E>) {
+        let id = entry.scheduled.id;
+        let node = Node {
+            tick: (self.tick)(&entry.time),
+            entry,
+            stamp: self.next_stamp,
+            place: Place::Due,
+            prev: None,
+            next: None,
+        };
+        self.next_stamp += 1;
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                index
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.ids.insert(id, index);
+        self.place(index);
+        self.settle();
+    }
+
+    fn first(&self) -> Option<&QueueEntry<Time, E>> {
+        self.due
+            .peek()
+            .map(|Reverse((.., index))| &self.node(*index).entry)
+    }
+
+    fn pop_first(&mut self) -> Option<QueueEntry<Time, E>> {
+        let Reverse((.., index)) = self.due.pop()?;
+        let entry = self.remove_index(index);
+        self.settle();
+        Some(entry)
+    }
+
+    fn get(&self, id: EventId) -> Option<&QueueEntry<Time, E>> {
+        self.ids.get(&id).map(|index| &self.node(*index).entry)
+    }
+
+    fn remove(&mut self, id: EventId) -> bool {
+        let Some(index) = self.ids.get(&id).copied() else {
+            return false;
+        };
+        self.remove_index(index);
+        self.settle();
+        true
+    }
+
+    fn retain(&mut self, keep: &mut dyn FnMut(&QueueEntry<Time, E>) -> bool) {
+        for index in 0..self.nodes.len() {
+            if matches!(&self.nodes[index], Some(node) if !keep(&node.entry)) {
+                self.remove_index(index);
+            }
+        }
+        self.settle();
+    }
+
+    fn entries(&self) -> Vec<&QueueEntry<Time, E>> {
+        self.nodes
+            .iter()
+            .flatten()
+            .map(|node| &node.entry)
+            .collect()
+    }
+
+    fn take(&mut self) -> Vec<QueueEntry<Time, E>> {
+        let nodes = std::mem::take(&mut self.nodes);
+        *self = Self::new(self.tick);
+        nodes.into_iter().flatten().map(|node| node.entry).collect()
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+}
@@ -0,0 +1,541 @@
+//! Typed settings used to construct clocks and schedulers from configuration.
+//!
+//! Settings are layered from any number of TOML, YAML or JSON files followed by environment
+//! variables. With the prefix `SIM`, the variable `SIM__CLOCK__TIME_DILATION=2.0` overrides
+//! `clock.time_dilation`. Every field is optional and falls back to the value in
+//! [`Settings::default`].
+//!
+//! ```toml
+//! [clock]
+//! kind = "real_time"
+//! relative_start_time_ms = 3600000
+//! time_dilation = 2.0
+//! running = true
+//!
+//! [scheduler]
+//! mode = "real_time"
+//! ```
+use std::{num::NonZeroUsize, path::Path, sync::Arc};
+
+use config::{builder::DefaultState, ConfigBuilder, Environment, File, FileFormat};
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::error::{ToolboxError, ToolboxResult};
+use crate::scheduler::event::Event;
+use crate::scheduler::internal::{
+    DiscreteEventOptions, DrainTracker, QueueBackend, SchedulerHandle, SchedulerOptions, SeekPolicy,
+};
+use crate::scheduler::notification::NotificationSender;
+use crate::time::{
+    validate_time_dilation, DiscreteClock, ManualClock, RealTimeSimClock, SimClock, SimTime,
+    StepSimClock, TimeDuration, WallTime,
+};
+
+/// The kind of simulation clock to construct.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockKind {
+    /// [`RealTimeSimClock`]
+    #[default]
+    RealTime,
+    /// [`StepSimClock`]
+    Step,
+    /// [`ManualClock`]
+    Manual,
+}
+
+/// How the scheduler advances through events.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulerMode {
+    /// Wait for each event to come due on the clock.
+    #[default]
+    RealTime,
+    /// Jump the clock directly to each event. Requires a [`ClockKind::Manual`] or
+    /// [`ClockKind::Step`] clock.
+    Discrete,
+}
+
+/// Settings for the simulation clock. See [`SimClock::start`].
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ClockSettings {
+    /// The kind of clock to construct.
+    pub kind: ClockKind,
+    /// The wall clock time the simulation started, in milliseconds since the UNIX epoch. The
+    /// current time is used when unset.
+    pub simulation_start_time_ms: Option<i64>,
+    /// The simulation time the clock starts at, in milliseconds.
+    pub relative_start_time_ms: u64,
+    /// The amount of pause time already accumulated, in milliseconds.
+    pub elapsed_pause_time_ms: i64,
+    /// Time dilation factor. Must be positive and finite.
+    pub time_dilation: f64,
+    /// Resume the clock once it is started. Otherwise the clock is left paused.
+    pub running: bool,
+}
+
+impl Default for ClockSettings {
+    fn default() -> Self {
+        Self {
+            kind: ClockKind::default(),
+            simulation_start_time_ms: None,
+            relative_start_time_ms: 0,
+            elapsed_pause_time_ms: 0,
+            time_dilation: 1.0,
+            running: false,
+        }
+    }
+}
+
+/// Settings for the scheduler.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct SchedulerSettings {
+    /// How the scheduler advances through events.
+    pub mode: SchedulerMode,
+    /// Discrete mode only. Stop once the next event would execute after this simulation time,
+    /// in milliseconds.
+    pub stop_time_ms: Option<u64>,
+    /// Discrete mode only. Wait for consumers to process every notification before advancing.
+    pub drain: bool,
+    /// Real time mode only. How events passed over by a forward seek are handled.
+    pub seek_policy: SeekPolicy,
+    /// Maximum number of event handlers running at once. Unlimited when unset.
+    pub handler_concurrency: Option<NonZeroUsize>,
+    /// Structure holding the pending events.
+    pub queue: QueueBackend,
+}
+
+/// Settings for a clock and the scheduler that runs on it.
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Settings {
+    /// Settings for the simulation clock.
+    pub clock: ClockSettings,
+    /// Settings for the scheduler.
+    pub scheduler: SchedulerSettings,
+}
+
+/// A clock that can be constructed from [`ClockSettings`].
+pub trait ConfiguredClock: SimClock + Default {
+    /// The [`ClockKind`] that selects this clock.
+    const KIND: ClockKind;
+}
+
+impl ConfiguredClock for RealTimeSimClock {
+    const KIND: ClockKind = ClockKind::RealTime;
+}
+
+impl ConfiguredClock for StepSimClock {
+    const KIND: ClockKind = ClockKind::Step;
+}
+
+impl ConfiguredClock for ManualClock {
+    const KIND: ClockKind = ClockKind::Manual;
+}
+
+impl Settings {
+    /// Check every field, reporting all invalid fields in a single
+    /// [`ToolboxError::InvalidSettings`].
+    pub fn validate(&self) -> ToolboxResult {
+        let mut invalid = Vec::new();
+        if let Err(err) = validate_time_dilation(self.clock.time_dilation) {
+            invalid.push(format!("clock.time_dilation: {err}"));
+        }
+        if let Some(start) = self.clock.simulation_start_time_ms {
+            if WallTime::from_timestamp_millis(start).is_none() {
+                invalid.push(format!(
+                    "clock.simulation_start_time_ms: {start} is out of range"
+                ));
+            }
+        }
+        if self.clock.elapsed_pause_time_ms < 0 {
+            invalid.push(format!(
+                "clock.elapsed_pause_time_ms: {} must not be negative",
+                self.clock.elapsed_pause_time_ms
+            ));
+        }
+        match self.scheduler.mode {
+            SchedulerMode::RealTime => {
+                if self.scheduler.stop_time_ms.is_some() {
+                    invalid.push(
+                        "scheduler.stop_time_ms: only supported in discrete mode".to_string(),
+                    );
+                }
+                if self.scheduler.drain {
+                    invalid.push("scheduler.drain: only supported in discrete mode".to_string());
+                }
+            }
+            SchedulerMode::Discrete => {
+                if !matches!(self.clock.kind, ClockKind::Manual | ClockKind::Step) {
+                    invalid.push(format!(
+                        "scheduler.mode: discrete mode requires a manual or step clock, not {:?}",
+                        self.clock.kind
+                    ));
+                }
+            }
+        }
+
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(ToolboxError::InvalidSettings(invalid))
+        }
+    }
+
+    /// Construct and start a clock of type `C`, which must match the configured clock kind.
+    pub fn build_clock<C: ConfiguredClock>(&self) -> ToolboxResult<Arc<RwLock<C>>> {
+        self.validate()?;
+        if self.clock.kind != C::KIND {
+            return Err(ToolboxError::InvalidSettings(vec![format!(
+                "clock.kind: configured {:?} but {:?} was requested",
+                self.clock.kind,
+                C::KIND
+            )]));
+        }
+
+        let simulation_start_time = match self.clock.simulation_start_time_ms {
+            Some(millis) => WallTime::from_timestamp_millis(millis).ok_or(
+                ToolboxError::InvalidSettings(vec![format!(
+                    "clock.simulation_start_time_ms: {millis} is out of range"
+                )]),
+            )?,
+            None => WallTime::now(),
+        };
+        let mut clock = C::default();
+        clock.start(
+            simulation_start_time,
+            SimTime::from_millis(self.clock.relative_start_time_ms),
+            TimeDuration::milliseconds(self.clock.elapsed_pause_time_ms),
+            self.clock.time_dilation,
+        );
+        if self.clock.running {
+            clock.resume();
+        }
+        Ok(Arc::new(RwLock::new(clock)))
+    }
+
+    /// Construct a real time scheduler on `clock`.
+    pub fn build_scheduler<C, E>(
+        &self,
+        clock: Arc<RwLock<C>>,
+        event_sender: impl Into<NotificationSender<C>>,
+    ) -> ToolboxResult<SchedulerHandle<C, E>>
+    where
+        C: ConfiguredClock + Send + Sync + 'static,
+        E: Event<C> + 'static,
+    {
+        self.validate()?;
+        if self.scheduler.mode != SchedulerMode::RealTime {
+            return Err(ToolboxError::InvalidSettings(vec![
+                "scheduler.mode: discrete schedulers are built with build_discrete_scheduler"
+                    .to_string(),
+            ]));
+        }
+        Ok(SchedulerHandle::with_options(
+            clock,
+            event_sender,
+            SchedulerOptions {
+                seek_policy: self.scheduler.seek_policy,
+                handler_concurrency: self.scheduler.handler_concurrency,
+            },
+        )
+        .with_queue(self.scheduler.queue))
+    }
+
+    /// Construct a discrete-event scheduler on `clock`. The [`DrainTracker`] is returned when
+    /// draining is enabled.
+    pub fn build_discrete_scheduler<C, E>(
+        &self,
+        clock: Arc<RwLock<C>>,
+        event_sender: impl Into<NotificationSender<C>>,
+    ) -> ToolboxResult<(SchedulerHandle<C, E>, Option<DrainTracker>)>
+    where
+        C: ConfiguredClock + DiscreteClock + Send + Sync + 'static,
+        E: Event<C> + 'static,
+    {
+        self.validate()?;
+        if self.scheduler.mode != SchedulerMode::Discrete {
+            return Err(ToolboxError::InvalidSettings(vec![
+                "scheduler.mode: real time schedulers are built with build_scheduler".to_string(),
+            ]));
+        }
+        let drain = self.scheduler.drain.then(DrainTracker::new);
+        let options = DiscreteEventOptions {
+            stop_time: self.scheduler.stop_time_ms.map(SimTime::from_millis),
+            drain: drain.clone(),
+            handler_concurrency: self.scheduler.handler_concurrency,
+        };
+        Ok((
+            SchedulerHandle::new_discrete(clock, event_sender, options)
+                .with_queue(self.scheduler.queue),
+            drain,
+        ))
+    }
+
+    /// Construct a [`RealTimeSimClock`] and a scheduler running on it.
+    #[allow(clippy::type_complexity)]
+    pub fn build<E: Event<RealTimeSimClock> + 'static>(
+        &self,
+        event_sender: impl Into<NotificationSender<RealTimeSimClock>>,
+    ) -> ToolboxResult<(
+        Arc<RwLock<RealTimeSimClock>>,
+        SchedulerHandle<RealTimeSimClock, E>,
+    )> {
+        let clock = self.build_clock::<RealTimeSimClock>()?;
+        let scheduler = self.build_scheduler(Arc::clone(&clock), event_sender)?;
+        Ok((clock, scheduler))
+    }
+}
+
+/// Loads [`Settings`] from layered configuration sources. Later sources override earlier ones.
+#[derive(Default)]
+pub struct SettingsBuilder {
+    config: ConfigBuilder<DefaultState>,
+}
+
+impl SettingsBuilder {
+    /// Create a builder with no configuration sources.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a required configuration file. The format is taken from the file extension.
+    pub fn file(mut self, path: impl AsRef<Path>) -> Self {
+        self.config = self.config.add_source(File::from(path.as_ref()));
+        self
+    }
+
+    /// Add configuration from a string in the given format.
+    pub fn contents(mut self, contents: &str, format: FileFormat) -> Self {
+        self.config = self.config.add_source(File::from_str(contents, format));
+        self
+    }
+
+    /// Add overrides from environment variables starting with `prefix`, using `__` to separate
+    /// nested keys.
+    pub fn env_prefix(self, prefix: &str) -> Self {
+        self.environment(Environment::with_prefix(prefix))
+    }
+
+    fn environment(mut self, environment: Environment) -> Self {
+        self.config = self
+            .config
+            .add_source(environment.separator("__").try_parsing(true));
+        self
+    }
+
+    /// Load and validate the settings. Fields with a value of the wrong type are all reported
+    /// in a single [`ToolboxError::InvalidSettings`], as are fields failing
+    /// [`Settings::validate`].
+    pub fn load(self) -> ToolboxResult<Settings> {
+        let config = self.config.build()?;
+        let settings: Settings = match config.clone().try_deserialize() {
+            Ok(settings) => settings,
+            Err(err) => {
+                let invalid = field_errors(&config.try_deserialize()?);
+                if invalid.is_empty() {
+                    return Err(err.into());
+                }
+                return Err(ToolboxError::InvalidSettings(invalid));
+            }
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+}
+
+/// Deserialize every field of every section on its own, describing each field that fails.
+fn field_errors(value: &serde_json::Value) -> Vec<String> {
+    let Some(sections) = value.as_object() else {
+        return Vec::new();
+    };
+    let mut invalid = Vec::new();
+    for (section, fields) in sections {
+        let check: fn(&serde_json::Value) -> Result<(), serde_json::Error> = match section.as_str()
+        {
+            "clock" => |value| ClockSettings::deserialize(value).map(drop),
+            "scheduler" => |value| SchedulerSettings::deserialize(value).map(drop),
+            _ => continue,
+        };
+        match fields.as_object() {
+            Some(fields) => {
+                for (name, field) in fields {
+                    let single = serde_json::json!({ name: field });
+                    if let Err(err) = check(&single) {
+                        invalid.push(format!("{section}.{name}: {err}"));
+                    }
+                }
+            }
+            None => {
+                if let Err(err) = check(fields) {
+                    invalid.push(format!("{section}: {err}"));
+                }
+            }
+        }
+    }
+    invalid
+}
+
+#[cfg(test)]
+mod settings_tests {
+    use std::collections::HashMap;
+
+    use config::{Environment, FileFormat};
+    use tokio::sync::mpsc;
+
+    use crate::error::ToolboxError;
+    use crate::settings::{ClockKind, SchedulerMode, Settings, SettingsBuilder};
+    use crate::time::{Clock, ManualClock, RealTimeSimClock, SimClock, SimTime, StepSimClock};
+
+    #[test]
+    pub fn missing_fields_use_defaults() {
+        let settings = SettingsBuilder::new()
+            .contents("", FileFormat::Toml)
+            .load()
+            .unwrap();
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    pub fn loads_each_file_format() {
+        let toml = "[clock]\nkind = \"step\"\ntime_dilation = 2.5\n";
+        let yaml = "clock:\n  kind: step\n  time_dilation: 2.5\n";
+        let json = r#"{"clock": {"kind": "step", "time_dilation": 2.5}}"#;
+        for (contents, format) in [
+            (toml, FileFormat::Toml),
+            (yaml, FileFormat::Yaml),
+            (json, FileFormat::Json),
+        ] {
+            let settings = SettingsBuilder::new()
+                .contents(contents, format)
+                .load()
+                .unwrap();
+            assert_eq!(settings.clock.kind, ClockKind::Step);
+            assert_eq!(settings.clock.time_dilation, 2.5);
+        }
+    }
+
+    #[test]
+    pub fn environment_overrides_files() {
+        let env = HashMap::from([
+            ("SIM__CLOCK__TIME_DILATION".to_string(), "4".to_string()),
+            ("SIM__SCHEDULER__MODE".to_string(), "discrete".to_string()),
+            ("SIM__CLOCK__KIND".to_string(), "manual".to_string()),
+        ]);
+        let settings = SettingsBuilder::new()
+            .contents("[clock]\ntime_dilation = 2.0\n", FileFormat::Toml)
+            .environment(Environment::with_prefix("SIM").source(Some(env)))
+            .load()
+            .unwrap();
+        assert_eq!(settings.clock.time_dilation, 4.0);
+        assert_eq!(settings.clock.kind, ClockKind::Manual);
+        assert_eq!(settings.scheduler.mode, SchedulerMode::Discrete);
+    }
+
+    #[test]
+    pub fn every_invalid_field_is_reported() {
+        let toml = "[clock]\ntime_dilation = -1.0\nelapsed_pause_time_ms = -5\n\
+                    [scheduler]\nstop_time_ms = 10\n";
+        let err = SettingsBuilder::new()
+            .contents(toml, FileFormat::Toml)
+            .load()
+            .unwrap_err();
+        let ToolboxError::InvalidSettings(invalid) = err else {
+            panic!("expected invalid settings, got {err}");
+        };
+        assert_eq!(invalid.len(), 3);
+        assert!(invalid[0].starts_with("clock.time_dilation"));
+        assert!(invalid[1].starts_with("clock.elapsed_pause_time_ms"));
+        assert!(invalid[2].starts_with("scheduler.stop_time_ms"));
+    }
+
+    #[test]
+    pub fn every_field_of_the_wrong_type_is_reported() {
+        let toml = "[clock]\nkind = \"sundial\"\ntime_dilation = \"fast\"\nrunning = true\n\
+                    [scheduler]\nhandler_concurrency = 0\n";
+        let err = SettingsBuilder::new()
+            .contents(toml, FileFormat::Toml)
+            .load()
+            .unwrap_err();
+        let ToolboxError::InvalidSettings(invalid) = err else {
+            panic!("expected invalid settings, got {err}");
+        };
+        assert_eq!(invalid.len(), 3, "{invalid:?}");
+        assert!(invalid.iter().any(|field| field.starts_with("clock.kind")));
+        assert!(invalid
+            .iter()
+            .any(|field| field.starts_with("clock.time_dilation")));
+        assert!(invalid
+            .iter()
+            .any(|field| field.starts_with("scheduler.handler_concurrency")));
+    }
+
+    #[test]
+    pub fn build_clock_checks_kind() {
+        let settings = Settings::default();
+        assert!(settings.build_clock::<StepSimClock>().is_err());
+        assert!(settings.build_clock::<RealTimeSimClock>().is_ok());
+    }
+
+    #[tokio::test]
+    async fn builds_started_clock_and_scheduler() {
+        let toml = "[clock]\nrelative_start_time_ms = 5000\nrunning = true\n";
+        let settings = SettingsBuilder::new()
+            .contents(toml, FileFormat::Toml)
+            .load()
+            .unwrap();
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        let (clock, scheduler) = settings.build::<NeverEvent>(sender).unwrap();
+        assert!(clock.read().await.is_running());
+        assert!(scheduler.now().await >= SimTime::from_seconds(5));
+    }
+
+    #[tokio::test]
+    async fn builds_discrete_scheduler() {
+        let toml = "[clock]\nkind = \"manual\"\n[scheduler]\nmode = \"discrete\"\n\
+                    stop_time_ms = 1000\ndrain = true\n";
+        let settings = SettingsBuilder::new()
+            .contents(toml, FileFormat::Toml)
+            .load()
+            .unwrap();
+        let clock = settings.build_clock::<ManualClock>().unwrap();
+        assert!(clock.read().await.is_paused());
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let (_scheduler, drain) = settings
+            .build_discrete_scheduler::<ManualClock, NeverEvent>(clock.clone(), sender)
+            .unwrap();
+        assert!(drain.is_some());
+        assert!(settings
+            .build_scheduler::<ManualClock, NeverEvent>(clock.clone(), mpsc::unbounded_channel().0)
+            .is_err());
+
+        clock.write().await.resume();
+        assert!(receiver.recv().await.is_none());
+        assert_eq!(clock.read().await.now(), SimTime::zero());
+    }
+
+    /// Event type for schedulers that are never given events.
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct NeverEvent;
+
+    impl<C: Clock<Time = SimTime>> crate::scheduler::event::Event<C> for NeverEvent {
+        fn name(&self) -> &str {
+            "never"
+        }
+
+        fn execution_time(&self) -> SimTime {
+            SimTime::zero()
+        }
+
+        fn next_time(&self) -> Self {
+            NeverEvent
+        }
+
+        fn count(&self, _new_count: u64) -> Self {
+            NeverEvent
+        }
+    }
+}
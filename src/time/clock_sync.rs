@@ -0,0 +1,518 @@
+//! This module synchronizes [`RealTimeSimClock`]s across services over UDP.
+//!
+//! A [`ClockMaster`] owns the authoritative clock. Every control operation is applied locally and
+//! broadcast to a unicast or multicast address, along with periodic time beacons. A
+//! [`ClockFollower`] applies the received operations to its local clock and uses the beacons to
+//! estimate and correct its offset from the master with [`SimClock::offset_by`].
+//!
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{net::UdpSocket, sync::RwLock, task::JoinHandle};
+
+use crate::error::ToolboxResult;
+use crate::time::{
+    Clock, ClockState, RealTimeSimClock, SimClock, SimDuration, SimTime, TimeDuration, TimeStamp,
+    WallTime,
+};
+
+/// Largest datagram expected from a [`ClockMaster`].
+const MAX_PACKET_SIZE: usize = 1024;
+
+/// Clock control operations sent from a [`ClockMaster`] to its followers.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncMessage {
+    /// The clock was started with the given parameters. See [`SimClock::start`].
+    Start {
+        simulation_start_time: TimeStamp,
+        relative_start_time: SimTime,
+        elapsed_pause_millis: i64,
+        time_dilation: f64,
+    },
+    /// The clock was paused.
+    Pause,
+    /// The clock was resumed.
+    Resume,
+    /// The clock was stopped.
+    Stop,
+    /// The time dilation factor changed.
+    TimeDilation { time_dilation: f64 },
+    /// The master's view of the clock when the beacon was sent.
+    Beacon {
+        sim_time: SimTime,
+        time_dilation: f64,
+        state: ClockState,
+    },
+}
+
+/// A [`SyncMessage`] with the sequence number used to discard stale datagrams. The session is
+/// the time a master was bound at, so that a restarted master is followed and delayed datagrams
+/// from an earlier master are discarded.
+#[derive(Serialize, Deserialize, Debug)]
+struct SyncPacket {
+    session: i64,
+    sequence: u64,
+    message: SyncMessage,
+}
+
+/// Owns the authoritative clock and broadcasts its changes to followers.
+pub struct ClockMaster {
+    clock: Arc<RwLock<RealTimeSimClock>>,
+    socket: UdpSocket,
+    target: SocketAddr,
+    session: i64,
+    sequence: AtomicU64,
+}
+
+impl ClockMaster {
+    /// Bind a master to `local` that sends to `target`. The target may be a unicast address or an
+    /// IPv4 multicast group.
+    pub async fn bind(
+        clock: Arc<RwLock<RealTimeSimClock>>,
+        local: SocketAddr,
+        target: SocketAddr,
+    ) -> ToolboxResult<Self> {
+        let socket = UdpSocket::bind(local).await?;
+        if target.ip().is_multicast() {
+            socket.set_multicast_loop_v4(true)?;
+        }
+        Ok(Self {
+            clock,
+            socket,
+            target,
+            session: WallTime::now().as_date_time().and_utc().timestamp_micros(),
+            sequence: AtomicU64::new(0),
+        })
+    }
+
+    /// Return the clock controlled by this master.
+    pub fn clock(&self) -> Arc<RwLock<RealTimeSimClock>> {
+        Arc::clone(&self.clock)
+    }
+
+    /// Start the local clock and broadcast the start parameters. See [`SimClock::start`].
+    pub async fn start(
+        &self,
+        simulation_start_time: WallTime,
+        relative_start_time: SimTime,
+        elapsed_pause_time: TimeDuration,
+        time_dilation: f64,
+    ) -> ToolboxResult {
+        self.clock.write().await.start(
+            simulation_start_time,
+            relative_start_time,
+            elapsed_pause_time,
+            time_dilation,
+        );
+        self.send(SyncMessage::Start {
+            simulation_start_time: simulation_start_time.into(),
+            relative_start_time,
+            elapsed_pause_millis: elapsed_pause_time.into(),
+            time_dilation,
+        })
+        .await
+    }
+
+    /// Pause the local clock and broadcast the pause.
+    pub async fn pause(&self) -> ToolboxResult {
+        self.clock.write().await.pause();
+        self.send(SyncMessage::Pause).await
+    }
+
+    /// Resume the local clock and broadcast the resume.
+    pub async fn resume(&self) -> ToolboxResult {
+        self.clock.write().await.resume();
+        self.send(SyncMessage::Resume).await
+    }
+
+    /// Stop the local clock and broadcast the stop.
+    pub async fn stop(&self) -> ToolboxResult {
+        self.clock.write().await.stop();
+        self.send(SyncMessage::Stop).await
+    }
+
+    /// Change the local time dilation and broadcast the new factor.
+    pub async fn set_time_dilation(&self, time_dilation: f64) -> ToolboxResult {
+        self.clock.write().await.set_time_dilation(time_dilation)?;
+        self.send(SyncMessage::TimeDilation { time_dilation }).await
+    }
+
+    /// Broadcast the current time of the local clock.
+    pub async fn send_beacon(&self) -> ToolboxResult {
+        let beacon = {
+            let clock = self.clock.read().await;
+            SyncMessage::Beacon {
+                sim_time: clock.now(),
+                time_dilation: clock.time_dilation(),
+                state: clock.state(),
+            }
+        };
+        self.send(beacon).await
+    }
+
+    /// Send a beacon every `interval` until the returned task is aborted or a send fails.
+    pub fn spawn_beacons(
+        self: Arc<Self>,
+        interval: std::time::Duration,
+    ) -> JoinHandle<ToolboxResult> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.send_beacon().await?;
+            }
+        })
+    }
+
+    async fn send(&self, message: SyncMessage) -> ToolboxResult {
+        let packet = SyncPacket {
+            session: self.session,
+            sequence: self.sequence.fetch_add(1, Ordering::Relaxed),
+            message,
+        };
+        let bytes = serde_json::to_vec(&packet)?;
+        self.socket.send_to(&bytes, self.target).await?;
+        Ok(())
+    }
+}
+
+/// Applies the changes broadcast by a [`ClockMaster`] to a local clock.
+pub struct ClockFollower {
+    clock: Arc<RwLock<RealTimeSimClock>>,
+    socket: UdpSocket,
+    /// Offsets from the master smaller than this are left uncorrected.
+    correction_threshold: SimDuration,
+    /// Estimated time a beacon takes to arrive from the master.
+    one_way_delay: TimeDuration,
+    last_sequence: Option<(i64, u64)>,
+    last_offset: Option<SimDuration>,
+}
+
+impl ClockFollower {
+    /// Bind a follower to `local`.
+    pub async fn bind(
+        clock: Arc<RwLock<RealTimeSimClock>>,
+        local: SocketAddr,
+    ) -> ToolboxResult<Self> {
+        Ok(Self {
+            clock,
+            socket: UdpSocket::bind(local).await?,
+            correction_threshold: SimDuration::milliseconds(1),
+            one_way_delay: TimeDuration::zero(),
+            last_sequence: None,
+            last_offset: None,
+        })
+    }
+
+    /// Only correct offsets from the master larger than `threshold`. Any error in the
+    /// [one-way delay](ClockFollower::with_one_way_delay) estimate shows up as an offset, so the
+    /// threshold should cover the variation in network latency.
+    pub fn with_correction_threshold(mut self, threshold: SimDuration) -> Self {
+        self.correction_threshold = threshold;
+        self
+    }
+
+    /// Assume beacons take `delay` to arrive from the master, during which a running master
+    /// clock moves on. Defaults to zero.
+    pub fn with_one_way_delay(mut self, delay: TimeDuration) -> Self {
+        self.one_way_delay = delay;
+        self
+    }
+
+    /// Receive messages sent to an IPv4 multicast `group` on `interface`.
+    pub fn join_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> ToolboxResult {
+        self.socket.join_multicast_v4(group, interface)?;
+        Ok(())
+    }
+
+    /// Return the address the follower is listening on.
+    pub fn local_addr(&self) -> ToolboxResult<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Return the offset of the local clock from the master, measured at the last beacon
+    /// allowing for the one-way delay, before any correction was applied. Positive values mean
+    /// the master was ahead.
+    pub fn last_offset(&self) -> Option<SimDuration> {
+        self.last_offset
+    }
+
+    /// Wait for the next message from the master and apply it to the local clock. Malformed and
+    /// out of order datagrams are discarded, as are datagrams from a master older than the one
+    /// last followed.
+    pub async fn recv(&mut self) -> ToolboxResult<SyncMessage> {
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        loop {
+            let length = self.socket.recv(&mut buffer).await?;
+            let Ok(packet) = serde_json::from_slice::<SyncPacket>(&buffer[..length]) else {
+                continue;
+            };
+            if self
+                .last_sequence
+                .is_some_and(|last| (packet.session, packet.sequence) <= last)
+            {
+                continue;
+            }
+            self.last_sequence = Some((packet.session, packet.sequence));
+            self.apply(&packet.message).await?;
+            return Ok(packet.message);
+        }
+    }
+
+    /// Apply messages from the master until an error occurs.
+    pub async fn run(mut self) -> ToolboxResult {
+        loop {
+            self.recv().await?;
+        }
+    }
+
+    /// Apply messages from the master on a background task.
+    pub fn spawn(self) -> JoinHandle<ToolboxResult> {
+        tokio::spawn(self.run())
+    }
+
+    async fn apply(&mut self, message: &SyncMessage) -> ToolboxResult {
+        let mut clock = self.clock.write().await;
+        match message {
+            SyncMessage::Start {
+                simulation_start_time,
+                relative_start_time,
+                elapsed_pause_millis,
+                time_dilation,
+            } => {
+                clock.start(
+                    WallTime::try_from(*simulation_start_time)?,
+                    *relative_start_time,
+                    TimeDuration::from(*elapsed_pause_millis),
+                    *time_dilation,
+                );
+                self.last_offset = None;
+            }
+            SyncMessage::Pause => clock.pause(),
+            SyncMessage::Resume => clock.resume(),
+            SyncMessage::Stop => clock.stop(),
+            SyncMessage::TimeDilation { time_dilation } => {
+                clock.set_time_dilation(*time_dilation)?;
+            }
+            SyncMessage::Beacon {
+                sim_time,
+                time_dilation,
+                state,
+            } => {
+                if clock.state() != *state {
+                    match state {
+                        ClockState::Running => clock.resume(),
+                        ClockState::Paused => clock.pause(),
+                        ClockState::Stopped => clock.stop(),
+                    }
+                }
+                if clock.time_dilation() != *time_dilation {
+                    clock.set_time_dilation(*time_dilation)?;
+                }
+                // A running master clock has moved on while the beacon was in transit.
+                let transit_micros = if *state == ClockState::Running {
+                    let delay_micros = self
+                        .one_way_delay
+                        .as_duration()
+                        .num_microseconds()
+                        .unwrap_or(i64::MAX);
+                    (delay_micros as f64 * clock.time_dilation()) as i64
+                } else {
+                    0
+                };
+                let offset_micros = (sim_time.as_micros() as i64).saturating_add(transit_micros)
+                    - clock.now().as_micros() as i64;
+                let offset = SimDuration::microseconds(offset_micros);
+                if offset_micros.abs() > self.correction_threshold.num_microseconds() {
+                    // Moving the start time forward moves the simulation time backwards.
+                    let wall_micros = (offset_micros as f64 / clock.time_dilation()) as i64;
+                    clock.offset_by(TimeDuration::microseconds(-wall_micros));
+                }
+                self.last_offset = Some(offset);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod clock_sync_tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use tokio::sync::RwLock;
+
+    use crate::time::clock_sync::{ClockFollower, ClockMaster, SyncMessage};
+    use crate::time::{
+        Clock, RealTimeSimClock, SimClock, SimDuration, SimTime, TimeDuration, WallTime,
+    };
+
+    async fn loopback_pair() -> (ClockMaster, ClockFollower) {
+        let any: SocketAddr = "127.0.0.1:0".parse().unwrap();
+        let follower = ClockFollower::bind(Arc::new(RwLock::new(RealTimeSimClock::default())), any)
+            .await
+            .unwrap();
+        let master = ClockMaster::bind(
+            Arc::new(RwLock::new(RealTimeSimClock::default())),
+            any,
+            follower.local_addr().unwrap(),
+        )
+        .await
+        .unwrap();
+        (master, follower)
+    }
+
+    #[tokio::test]
+    async fn follower_applies_master_controls() {
+        let (master, mut follower) = loopback_pair().await;
+        let start = WallTime::now();
+        master
+            .start(start, SimTime::from_seconds(100), TimeDuration::zero(), 1.0)
+            .await
+            .unwrap();
+        assert!(matches!(
+            follower.recv().await.unwrap(),
+            SyncMessage::Start { .. }
+        ));
+        {
+            let clock = follower.clock.read().await;
+            assert!(clock.is_paused());
+            assert_eq!(
+                clock.simulation_start_time().timestamp_millis(),
+                start.timestamp_millis()
+            );
+        }
+
+        master.resume().await.unwrap();
+        assert_eq!(follower.recv().await.unwrap(), SyncMessage::Resume);
+        assert!(follower.clock.read().await.is_running());
+
+        master.set_time_dilation(4.0).await.unwrap();
+        follower.recv().await.unwrap();
+        assert_eq!(follower.clock.read().await.time_dilation(), 4.0);
+
+        master.pause().await.unwrap();
+        assert_eq!(follower.recv().await.unwrap(), SyncMessage::Pause);
+        assert!(follower.clock.read().await.is_paused());
+    }
+
+    #[tokio::test]
+    async fn beacon_corrects_follower_offset() {
+        let (master, mut follower) = loopback_pair().await;
+        master
+            .start(WallTime::now(), SimTime::zero(), TimeDuration::zero(), 2.0)
+            .await
+            .unwrap();
+        follower.recv().await.unwrap();
+        master.pause().await.unwrap();
+        follower.recv().await.unwrap();
+
+        // Drift the follower five seconds ahead of the master.
+        follower
+            .clock
+            .write()
+            .await
+            .offset_by(TimeDuration::milliseconds(-2_500));
+
+        master.send_beacon().await.unwrap();
+        follower.recv().await.unwrap();
+
+        let offset = follower.last_offset().unwrap();
+        assert!((offset.num_milliseconds() + 5_000).abs() <= 1);
+        let drift = follower.clock.read().await.now().as_micros() as i64
+            - master.clock().read().await.now().as_micros() as i64;
+        assert!(drift.abs() <= SimDuration::milliseconds(1).num_microseconds());
+    }
+
+    #[tokio::test]
+    async fn beacon_offset_allows_for_one_way_delay() {
+        let (master, follower) = loopback_pair().await;
+        let mut follower = follower.with_correction_threshold(SimDuration::seconds(60));
+        master
+            .start(WallTime::now(), SimTime::zero(), TimeDuration::zero(), 2.0)
+            .await
+            .unwrap();
+        follower.recv().await.unwrap();
+        master.resume().await.unwrap();
+        follower.recv().await.unwrap();
+
+        master.send_beacon().await.unwrap();
+        follower.recv().await.unwrap();
+        let measured = follower.last_offset().unwrap();
+
+        let mut follower = follower.with_one_way_delay(TimeDuration::milliseconds(5_000));
+        master.send_beacon().await.unwrap();
+        follower.recv().await.unwrap();
+        // The master clock runs at twice wall clock speed while the beacon is in transit.
+        let compensated =
+            follower.last_offset().unwrap().num_milliseconds() - measured.num_milliseconds();
+        assert!((compensated - 10_000).abs() < 1_000);
+    }
+
+    #[tokio::test]
+    async fn stale_packets_are_discarded() {
+        let (master, mut follower) = loopback_pair().await;
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        master.resume().await.unwrap();
+        follower.recv().await.unwrap();
+
+        let stale = format!(
+            r#"{{"session":{},"sequence":0,"message":{{"type":"stop"}}}}"#,
+            master.session
+        );
+        let earlier_master = format!(
+            r#"{{"session":{},"sequence":100,"message":{{"type":"stop"}}}}"#,
+            master.session - 1
+        );
+        for packet in [stale, earlier_master] {
+            socket
+                .send_to(packet.as_bytes(), follower.local_addr().unwrap())
+                .await
+                .unwrap();
+        }
+        socket
+            .send_to(b"not json", follower.local_addr().unwrap())
+            .await
+            .unwrap();
+        master.pause().await.unwrap();
+
+        assert_eq!(follower.recv().await.unwrap(), SyncMessage::Pause);
+        assert!(follower.clock.read().await.is_paused());
+    }
+
+    #[tokio::test]
+    async fn follower_receives_multicast() {
+        let group = std::net::Ipv4Addr::new(239, 255, 42, 99);
+        let follower = ClockFollower::bind(
+            Arc::new(RwLock::new(RealTimeSimClock::default())),
+            "0.0.0.0:0".parse().unwrap(),
+        )
+        .await
+        .unwrap();
+        follower
+            .join_multicast_v4(group, std::net::Ipv4Addr::LOCALHOST)
+            .unwrap();
+        let port = follower.local_addr().unwrap().port();
+        let master = ClockMaster::bind(
+            Arc::new(RwLock::new(RealTimeSimClock::default())),
+            "127.0.0.1:0".parse().unwrap(),
+            SocketAddr::from((group, port)),
+        )
+        .await
+        .unwrap();
+        let mut follower = follower;
+
+        master.resume().await.unwrap();
+        let message = tokio::time::timeout(std::time::Duration::from_secs(1), follower.recv())
+            .await
+            .expect("multicast message should arrive over loopback")
+            .unwrap();
+        assert_eq!(message, SyncMessage::Resume);
+    }
+}
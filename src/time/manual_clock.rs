@@ -0,0 +1,211 @@
+//! This module contains a simulation clock that is moved explicitly by test code.
+//! Each advance immediately wakes any scheduler using the clock so that every event that falls
+//! due is fired without waiting on wall clock time.
+//!
+use tokio::sync::watch;
+
+use crate::error::ToolboxResult;
+use crate::time::{
+    validate_time_dilation, Clock, ClockChanges, ClockState, DiscreteClock, SimClock, SimDuration,
+    SimTime, TimeDuration, WallTime,
+};
+
+/// `ManualClock` is a deterministic simulation clock intended for tests. Time only moves when
+/// [`ManualClock::advance_to`] or [`ManualClock::advance_by`] is called.
+pub struct ManualClock {
+    /// Reported start time of the simulation.
+    relative_start_time: SimTime,
+    /// Current simulation time of the clock.
+    current_time: SimTime,
+    /// Current state of the clock.
+    state: ClockState,
+    /// Announces advances and state changes to subscribed schedulers.
+    changes: watch::Sender<u64>,
+}
+
+impl ManualClock {
+    /// Create a running clock at the given time.
+    ///
+    /// # Arguments
+    /// * `start_time` - The initial simulation time of the clock.
+    pub fn new(start_time: SimTime) -> Self {
+        Self {
+            relative_start_time: start_time,
+            current_time: start_time,
+            state: ClockState::Running,
+            changes: watch::channel(0).0,
+        }
+    }
+
+    /// Move the clock forward to `to` and wake any scheduler using the clock. Times in the past
+    /// are ignored, as are advances while the clock is not running.
+    ///
+    /// # Returns
+    /// The simulation time after the advance has been applied.
+    pub fn advance_to(&mut self, to: SimTime) -> SimTime {
+        if self.is_running() && to > self.current_time {
+            self.current_time = to;
+            self.changes.send_modify(|_| {});
+        }
+        self.current_time
+    }
+
+    /// Move the clock forward by `by` and wake any scheduler using the clock.
+    ///
+    /// # Returns
+    /// The simulation time after the advance has been applied.
+    pub fn advance_by(&mut self, by: SimDuration) -> SimTime {
+        self.advance_to(self.current_time + by)
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new(SimTime::zero())
+    }
+}
+
+impl Clock for ManualClock {
+    type Time = SimTime;
+    fn now(&self) -> Self::Time {
+        self.current_time
+    }
+
+    /// Returns the simulation time remaining until `then` as a wall clock delay. The clock never
+    /// advances on its own, so waking after this delay is harmless and advances wake the
+    /// scheduler through [`Clock::changes`].
+    fn delay_time(&self, then: Self::Time) -> TimeDuration {
+        TimeDuration::milliseconds((then - self.now()).num_milliseconds())
+    }
+
+    fn changes(&self) -> Option<ClockChanges> {
+        Some(self.changes.subscribe())
+    }
+
+    fn is_halted(&self) -> bool {
+        !self.is_running()
+    }
+
+    fn fires_missed_occurrences(&self) -> bool {
+        true
+    }
+}
+
+impl DiscreteClock for ManualClock {
+    fn jump_to(&mut self, time: Self::Time) {
+        self.advance_to(time);
+    }
+}
+
+impl SimClock for ManualClock {
+    /// Resets the clock to `relative_start_time`. The clock is left paused.
+    fn start(
+        &mut self,
+        _simulation_start_time: WallTime,
+        relative_start_time: SimTime,
+        _elapsed_pause_time: TimeDuration,
+        _time_dilation: f64,
+    ) {
+        self.relative_start_time = relative_start_time;
+        self.current_time = relative_start_time;
+        self.state = ClockState::Paused;
+        self.changes.send_modify(|_| {});
+    }
+
+    /// Moves the clock forwards (positive) or backwards (negative) by the specified duration,
+    /// regardless of the clock state. The clock never moves before the relative start time.
+    /// Moving backwards is announced as a seek, so that schedulers re-arm recurring events.
+    fn offset_by(&mut self, by: TimeDuration) {
+        let micros = by
+            .as_duration()
+            .num_microseconds()
+            .expect("TimeDuration should be valid microseconds");
+        let current = self.current_time.as_micros() as i64 + micros;
+        let previous = self.current_time;
+        self.current_time =
+            SimTime::from_micros(current.max(0) as u64).max(self.relative_start_time);
+        let backwards = self.current_time < previous;
+        self.changes
+            .send_modify(|seeks| *seeks += u64::from(backwards));
+    }
+
+    /// Validates the time dilation. Manual clocks only move when advanced, so it has no effect.
+    fn set_time_dilation(&mut self, time_dilation: f64) -> ToolboxResult {
+        validate_time_dilation(time_dilation).map(|_| ())
+    }
+
+    /// Moves the clock to `to` regardless of the clock state. Unlike an advance, a seek may move
+    /// the clock backwards and lets the scheduler apply its seek policy.
+    fn seek(&mut self, to: SimTime) {
+        self.current_time = to;
+        self.changes.send_modify(|seeks| *seeks += 1);
+    }
+
+    fn pause(&mut self) {
+        self.state = ClockState::Paused;
+        self.changes.send_modify(|_| {});
+    }
+
+    fn resume(&mut self) {
+        self.state = ClockState::Running;
+        self.changes.send_modify(|_| {});
+    }
+
+    fn stop(&mut self) {
+        self.state = ClockState::Stopped;
+        self.changes.send_modify(|_| {});
+    }
+
+    fn elapsed(&self) -> SimDuration {
+        self.now() - self.relative_start_time
+    }
+
+    fn is_paused(&self) -> bool {
+        self.state == ClockState::Paused
+    }
+
+    fn is_running(&self) -> bool {
+        self.state == ClockState::Running
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.state == ClockState::Stopped
+    }
+}
+
+#[cfg(test)]
+mod manual_clock_tests {
+    use crate::time::manual_clock::ManualClock;
+    use crate::time::{Clock, SimClock, SimDuration, SimTime};
+
+    #[test]
+    pub fn advance_moves_clock_forward_only() {
+        let mut clock = ManualClock::default();
+        assert_eq!(
+            clock.advance_by(SimDuration::seconds(5)),
+            SimTime::from_seconds(5)
+        );
+        assert_eq!(
+            clock.advance_to(SimTime::from_seconds(2)),
+            SimTime::from_seconds(5)
+        );
+        assert_eq!(clock.elapsed(), 5_000);
+    }
+
+    #[test]
+    pub fn advance_is_ignored_while_paused() {
+        let mut clock = ManualClock::default();
+        clock.pause();
+        clock.advance_by(SimDuration::seconds(5));
+        assert_eq!(clock.now(), SimTime::zero());
+    }
+
+    #[test]
+    pub fn advance_notifies_subscribers() {
+        let mut clock = ManualClock::default();
+        let changes = clock.changes().expect("manual clock publishes changes");
+        assert!(!changes.has_changed().unwrap());
+        clock.advance_by(SimDuration::milliseconds(1));
+        assert!(changes.has_changed().unwrap());
+    }
+}
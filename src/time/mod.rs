@@ -3,12 +3,40 @@ The time module handles timing and scheduling of events based on simulation time
 The simulation clock and either by time step based or real (wall) clock time. The simulation clock operates at
 millisecond resolution as an offset from the Unix timestamp, i.e. January 1st 1970 at midnight.
  */
+use crate::error::{ToolboxError, ToolboxResult};
+
+mod clock_sync;
+mod manual_clock;
 mod real_time;
 mod real_time_sim_clock;
 mod sim_time;
+mod step_sim_clock;
+mod wall_clock;
 
+pub use crate::time::clock_sync::{ClockFollower, ClockMaster, SyncMessage};
+pub use crate::time::manual_clock::ManualClock;
 pub use crate::time::real_time::{TimeDuration, TimeStamp, WallTime};
+pub use crate::time::real_time_sim_clock::{ClockSnapshot, RealTimeSimClock};
 pub use crate::time::sim_time::{SimDuration, SimTime};
+pub use crate::time::step_sim_clock::StepSimClock;
+pub use crate::time::wall_clock::SystemWallClock;
+
+/// Check that `time_dilation` is usable as a time dilation factor.
+pub(crate) fn validate_time_dilation(time_dilation: f64) -> ToolboxResult<f64> {
+    if time_dilation.is_finite() && time_dilation > 0.0 {
+        Ok(time_dilation)
+    } else {
+        Err(ToolboxError::InvalidTimeDilation(time_dilation))
+    }
+}
+
+/// A time that can be placed on the timing wheel of a scheduler's event queue. Only required of
+/// the time of clocks whose scheduler uses
+/// [`QueueBackend::TimingWheel`](crate::scheduler::internal::QueueBackend::TimingWheel).
+pub trait TickTime {
+    /// Return the number of whole milliseconds since the Unix epoch, or zero for earlier times.
+    fn tick(&self) -> u64;
+}
 
 /// SimClock trait that extends Clock
 pub trait SimClock: Clock<Time = SimTime> {
@@ -17,12 +45,12 @@ pub trait SimClock: Clock<Time = SimTime> {
     /// # Arguments
     ///  
     /// * `simulation_start_time` - The wall clock time when the simulation was started. Used to sync
-    ///     clocks across services.
+    ///   clocks across services.
     /// * `relative_start_time` - The virtual time of the simulation. Use to have the simulation occur
-    ///     on a particular date/time.
+    ///   on a particular date/time.
     /// * `elapsed_pause_time` - The amount of pause time accumulated
     /// * `time_dilation` - Time dilation factor. Greater than 1 is faster than real time and less
-    ///     than 1 is slower than real time.
+    ///   than 1 is slower than real time.
     fn start(
         &mut self,
         simulation_start_time: WallTime,
@@ -37,6 +65,19 @@ pub trait SimClock: Clock<Time = SimTime> {
     ///  
     /// * `by` - The amount to adjust the clock.    
     fn offset_by(&mut self, by: TimeDuration);
+    /// Change the time dilation factor without a discontinuity in the current time. Only future
+    /// time advances and delay times are affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `time_dilation` - Time dilation factor. Must be positive and finite.
+    fn set_time_dilation(&mut self, time_dilation: f64) -> ToolboxResult;
+    /// Jump the clock forwards or backwards so that it reads `to`. The clock state is unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `to` - The simulation time to move to.
+    fn seek(&mut self, to: SimTime);
     /// Pause the simulation clock.
     fn pause(&mut self);
     /// Resume the simulation clock. Clock must currently be paused when resume is called.
@@ -70,10 +111,39 @@ pub trait Clock {
     ///
     /// * `then` - The time to wait until.
     fn delay_time(&self, then: Self::Time) -> TimeDuration;
+
+    /// Return a receiver that is notified whenever the clock changes in a way that invalidates
+    /// previously computed delay times. Clocks that only move with wall time return `None`.
+    fn changes(&self) -> Option<ClockChanges> {
+        None
+    }
+
+    /// Return `true` if the clock is paused or stopped. Schedulers do not fire events while the
+    /// clock is halted.
+    fn is_halted(&self) -> bool {
+        false
+    }
+
+    /// Return `true` if the scheduler should fire every occurrence of a recurring event that
+    /// falls due when the clock jumps forward, rather than skipping to the next future occurrence.
+    fn fires_missed_occurrences(&self) -> bool {
+        false
+    }
+}
+
+/// A [`Clock`] whose time can be set directly by a discrete-event scheduler.
+pub trait DiscreteClock: Clock {
+    /// Move the clock to `time`. Ignored while the clock is halted.
+    fn jump_to(&mut self, time: Self::Time);
 }
 
+/// Receiver used by a [`Clock`] to announce that its time or state was changed by a controller.
+/// The value counts the number of times the clock was moved with [`SimClock::seek`], allowing a
+/// scheduler to tell jumps in time apart from other changes.
+pub type ClockChanges = tokio::sync::watch::Receiver<u64>;
+
 /// The states that the clock may be in.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum ClockState {
     /// The clock is currently moving forward in time
     Running,
@@ -1,9 +1,9 @@
 use crate::error::ToolboxError;
-use crate::time::SimTime;
+use crate::time::{SimTime, TickTime};
 
 /// This type represents a [`WallTime`] time stamp as a microsecond offset as
 /// [`WallTime`] is not serializable.
-#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
 pub struct TimeStamp(i64);
 
 /// Wrapper type around the underlying duration type.
@@ -25,6 +25,25 @@ impl TimeDuration {
         Self(chrono::Duration::milliseconds(millis))
     }
 
+    /// Returns a `TimeDuration` with microsecond duration
+    ///
+    /// # Arguments
+    ///
+    /// * `micros` - The number of microseconds duration
+    pub fn microseconds(micros: i64) -> Self {
+        Self(chrono::Duration::microseconds(micros))
+    }
+
+    /// Returns a `TimeDuration` with nanosecond duration
+    pub fn nanoseconds(nanos: i64) -> Self {
+        Self(chrono::Duration::nanoseconds(nanos))
+    }
+
+    /// Returns the number of nanoseconds, or `None` on overflow
+    pub fn num_nanoseconds(&self) -> Option<i64> {
+        self.0.num_nanoseconds()
+    }
+
     pub(crate) fn as_duration(&self) -> chrono::Duration {
         self.0
     }
@@ -99,7 +118,7 @@ impl WallTime {
     /// Returns `None` on out-of-range number of milliseconds, otherwise returns `Some(WallTime)`.
     ///
     /// ```
-    /// use vct_utils::time::WallTime;
+    /// use sample::time::WallTime;
     ///
     /// let dt = WallTime::from_timestamp_millis(947638923004).expect("invalid timestamp");
     /// ```
@@ -108,6 +127,16 @@ impl WallTime {
         Some(WallTime(t.naive_utc()))
     }
 
+    /// Makes a new `WallTime` from the number of non-leap nanoseconds since the UNIX epoch.
+    pub fn from_timestamp_nanos(nanos: i64) -> WallTime {
+        WallTime(chrono::DateTime::from_timestamp_nanos(nanos).naive_utc())
+    }
+
+    /// Return as a nanosecond offset, or `None` outside the years 1677 to 2262
+    pub fn timestamp_nanos(&self) -> Option<i64> {
+        self.0.and_utc().timestamp_nanos_opt()
+    }
+
     /// Return time stamp as a [`chrono::NaiveDateTime`]
     pub fn as_date_time(&self) -> chrono::NaiveDateTime {
         self.0
@@ -119,6 +148,12 @@ impl WallTime {
     }
 }
 
+impl TickTime for WallTime {
+    fn tick(&self) -> u64 {
+        self.timestamp_millis().max(0) as u64
+    }
+}
+
 impl Default for WallTime {
     fn default() -> Self {
         Self::now()
@@ -137,6 +172,22 @@ impl std::ops::AddAssign<TimeDuration> for WallTime {
     }
 }
 
+impl std::ops::Add<TimeDuration> for WallTime {
+    type Output = WallTime;
+
+    fn add(self, rhs: TimeDuration) -> Self::Output {
+        WallTime(self.0 + rhs.0)
+    }
+}
+
+impl std::ops::Sub<TimeDuration> for WallTime {
+    type Output = WallTime;
+
+    fn sub(self, rhs: TimeDuration) -> Self::Output {
+        WallTime(self.0 - rhs.0)
+    }
+}
+
 impl std::ops::Sub for WallTime {
     type Output = TimeDuration;
 
@@ -1,7 +1,38 @@
 //! This module contains a simulation clock that operates on a multiple of real time.
 //! By default, it operates at a 1 to 1 scale, but can be sped up or slowed down.
 //!
-use crate::time::{Clock, ClockState, SimClock, SimDuration, SimTime, TimeDuration, WallTime};
+use tokio::sync::watch;
+
+use crate::error::{ToolboxError, ToolboxResult};
+use crate::time::{
+    validate_time_dilation, Clock, ClockChanges, ClockState, SimClock, SimDuration, SimTime,
+    TimeDuration, WallTime,
+};
+
+/// Serializable state of a [`RealTimeSimClock`], used to persist a clock or to transfer it to a
+/// service joining a running simulation. Wall clock times and durations are kept at nanosecond
+/// resolution so that a restored clock reports exactly the same time.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ClockSnapshot {
+    /// start time of the simulation as a nanosecond UNIX timestamp
+    pub simulation_start_nanos: i64,
+    /// Reported start time of the simulation.
+    pub relative_start_time: SimTime,
+    /// Total paused time in nanoseconds.
+    pub paused_nanos: i64,
+    /// Time dilation factor.
+    pub time_dilation: f64,
+    /// Unpaused wall clock nanoseconds elapsed when the time dilation took effect.
+    pub dilation_start_elapsed_nanos: i64,
+    /// Simulation nanoseconds elapsed when the time dilation took effect.
+    pub dilation_start_sim_elapsed_nanos: i64,
+    /// State of the clock.
+    pub state: ClockState,
+    /// The nanosecond UNIX timestamp that pause began, if the clock was paused or stopped.
+    pub pause_start_nanos: Option<i64>,
+    /// Simulation time when the snapshot was taken. Informational only.
+    pub sim_time: SimTime,
+}
 
 /// `RealTimeSimClock` is a simulation clock that operates at a multiple of real time.
 pub struct RealTimeSimClock {
@@ -14,10 +45,16 @@ pub struct RealTimeSimClock {
     /// Used to run at a non-real-time speed. Values > 1 indicate faster than real-time factor and < 1 indicate slow down
     /// factor. Value must be > 0.
     time_dilation: f64,
+    /// Unpaused wall clock time elapsed when the current time dilation took effect.
+    dilation_start_elapsed: TimeDuration,
+    /// Simulation time elapsed when the current time dilation took effect.
+    dilation_start_sim_elapsed: TimeDuration,
     /// Current state of the clock.
     state: ClockState,
     /// The wall clock time that pause began, or None if the clock is not paused.
     pause_start_time: Option<WallTime>,
+    /// Announces control changes to subscribed schedulers.
+    changes: watch::Sender<u64>,
 }
 
 impl RealTimeSimClock {
@@ -30,6 +67,90 @@ impl RealTimeSimClock {
     pub fn pause_time(&self) -> TimeDuration {
         self.paused_time
     }
+
+    /// Return the current state of the clock
+    pub fn state(&self) -> ClockState {
+        self.state
+    }
+
+    /// Return the current time dilation factor
+    pub fn time_dilation(&self) -> f64 {
+        self.time_dilation
+    }
+
+    /// Capture the state of the clock.
+    ///
+    /// # Errors
+    /// Returns an error if a wall clock time or duration cannot be represented in nanoseconds.
+    pub fn snapshot(&self) -> ToolboxResult<ClockSnapshot> {
+        Ok(ClockSnapshot {
+            simulation_start_nanos: timestamp_nanos(self.simulation_start_time)?,
+            relative_start_time: self.relative_start_time,
+            paused_nanos: duration_nanos(self.paused_time)?,
+            time_dilation: self.time_dilation,
+            dilation_start_elapsed_nanos: duration_nanos(self.dilation_start_elapsed)?,
+            dilation_start_sim_elapsed_nanos: duration_nanos(self.dilation_start_sim_elapsed)?,
+            state: self.state,
+            pause_start_nanos: self.pause_start_time.map(timestamp_nanos).transpose()?,
+            sim_time: self.now(),
+        })
+    }
+
+    /// Replace the state of the clock with a snapshot. The restored clock is aligned to wall
+    /// clock time, so if the snapshot was taken while running the time that passed since then is
+    /// included in the current time, as it would be for every other service in the simulation.
+    /// Take the snapshot while paused to resume from exactly the snapshot time.
+    ///
+    /// # Errors
+    /// Returns an error if the time dilation in the snapshot is invalid. The clock is left
+    /// unchanged in that case.
+    pub fn restore(&mut self, snapshot: &ClockSnapshot) -> ToolboxResult {
+        self.time_dilation = validate_time_dilation(snapshot.time_dilation)?;
+        self.simulation_start_time =
+            WallTime::from_timestamp_nanos(snapshot.simulation_start_nanos);
+        self.relative_start_time = snapshot.relative_start_time;
+        self.paused_time = TimeDuration::nanoseconds(snapshot.paused_nanos);
+        self.dilation_start_elapsed =
+            TimeDuration::nanoseconds(snapshot.dilation_start_elapsed_nanos);
+        self.dilation_start_sim_elapsed =
+            TimeDuration::nanoseconds(snapshot.dilation_start_sim_elapsed_nanos);
+        self.state = snapshot.state;
+        self.pause_start_time = snapshot
+            .pause_start_nanos
+            .map(WallTime::from_timestamp_nanos);
+        self.changes.send_modify(|_| {});
+        Ok(())
+    }
+
+    /// Wall clock time elapsed since the simulation started, excluding paused time.
+    fn unpaused_elapsed(&self, rt_now: WallTime) -> TimeDuration {
+        let current_pause = if let Some(pause) = self.pause_start_time {
+            rt_now - pause
+        } else {
+            TimeDuration::milliseconds(0)
+        };
+        rt_now - self.simulation_start_time - self.paused_time - current_pause
+    }
+
+    /// Simulation time elapsed since the simulation started.
+    fn sim_elapsed(&self, rt_now: WallTime) -> TimeDuration {
+        let mut elapsed = self.dilation_start_sim_elapsed;
+        elapsed +=
+            (self.unpaused_elapsed(rt_now) - self.dilation_start_elapsed) * self.time_dilation;
+        elapsed
+    }
+}
+
+fn timestamp_nanos(time: WallTime) -> ToolboxResult<i64> {
+    time.timestamp_nanos().ok_or(ToolboxError::Conversion(
+        "Could not convert WallTime to a nanosecond timestamp".to_string(),
+    ))
+}
+
+fn duration_nanos(duration: TimeDuration) -> ToolboxResult<i64> {
+    duration.num_nanoseconds().ok_or(ToolboxError::Conversion(
+        "Could not convert TimeDuration to nanoseconds".to_string(),
+    ))
 }
 
 impl Default for RealTimeSimClock {
@@ -40,8 +161,11 @@ impl Default for RealTimeSimClock {
             relative_start_time: SimTime::from_seconds(0),
             paused_time: TimeDuration::zero(),
             time_dilation: 1.0,
+            dilation_start_elapsed: TimeDuration::zero(),
+            dilation_start_sim_elapsed: TimeDuration::zero(),
             state: ClockState::Stopped,
             pause_start_time: None,
+            changes: watch::channel(0).0,
         }
     }
 }
@@ -49,14 +173,7 @@ impl Default for RealTimeSimClock {
 impl Clock for RealTimeSimClock {
     type Time = SimTime;
     fn now(&self) -> Self::Time {
-        let rt_now = WallTime::now();
-        let current_pause = if let Some(pause) = self.pause_start_time {
-            rt_now - pause
-        } else {
-            TimeDuration::milliseconds(0)
-        };
-        let execution_time = rt_now - self.simulation_start_time - self.paused_time - current_pause;
-        self.relative_start_time + execution_time * self.time_dilation
+        self.relative_start_time + self.sim_elapsed(WallTime::now())
     }
 
     /// Calculates the delay time for a future event, taking into account the current time dilation factor.
@@ -76,6 +193,16 @@ impl Clock for RealTimeSimClock {
             TimeDuration::zero()
         }
     }
+
+    /// Returns a receiver that is notified whenever the clock is started, offset, paused,
+    /// resumed or stopped.
+    fn changes(&self) -> Option<ClockChanges> {
+        Some(self.changes.subscribe())
+    }
+
+    fn is_halted(&self) -> bool {
+        !self.is_running()
+    }
 }
 
 impl SimClock for RealTimeSimClock {
@@ -96,8 +223,11 @@ impl SimClock for RealTimeSimClock {
         self.relative_start_time = relative_start_time;
         self.paused_time = elapsed_pause_time;
         self.time_dilation = time_dilation;
+        self.dilation_start_elapsed = TimeDuration::zero();
+        self.dilation_start_sim_elapsed = TimeDuration::zero();
         self.state = ClockState::Paused;
         self.pause_start_time = Some(WallTime::now());
+        self.changes.send_modify(|_| {});
     }
 
     /// Adjusts the simulation start time by a specified duration. This can be used to move the simulation's
@@ -105,8 +235,43 @@ impl SimClock for RealTimeSimClock {
     ///
     /// # Arguments
     /// * `by` - The duration to offset the simulation start time.
+    ///
+    /// A positive offset moves the simulation time backwards and is announced as a seek, so that
+    /// schedulers re-arm recurring events.
     fn offset_by(&mut self, by: TimeDuration) {
         self.simulation_start_time += by;
+        let backwards = by > TimeDuration::zero();
+        self.changes
+            .send_modify(|seeks| *seeks += u64::from(backwards));
+    }
+
+    /// Changes the time dilation factor. The clock is re-based at the current time so that `now`
+    /// is continuous across the change and only later advances use the new factor.
+    ///
+    /// # Arguments
+    /// * `time_dilation` - The new time dilation factor. Must be positive and finite.
+    fn set_time_dilation(&mut self, time_dilation: f64) -> ToolboxResult {
+        let time_dilation = validate_time_dilation(time_dilation)?;
+        let rt_now = WallTime::now();
+        self.dilation_start_sim_elapsed = self.sim_elapsed(rt_now);
+        self.dilation_start_elapsed = self.unpaused_elapsed(rt_now);
+        self.time_dilation = time_dilation;
+        self.changes.send_modify(|_| {});
+        Ok(())
+    }
+
+    /// Jumps the clock to `to` by re-basing the simulation time at the current wall clock time.
+    /// Pause time and time dilation are unaffected.
+    ///
+    /// # Arguments
+    /// * `to` - The simulation time to move to.
+    fn seek(&mut self, to: SimTime) {
+        let rt_now = WallTime::now();
+        self.dilation_start_elapsed = self.unpaused_elapsed(rt_now);
+        self.dilation_start_sim_elapsed = TimeDuration::microseconds(
+            to.as_micros() as i64 - self.relative_start_time.as_micros() as i64,
+        );
+        self.changes.send_modify(|seeks| *seeks += 1);
     }
 
     /// Pauses the simulation clock. This method records the current time as the pause start time, effectively
@@ -114,14 +279,16 @@ impl SimClock for RealTimeSimClock {
     fn pause(&mut self) {
         self.state = ClockState::Paused;
         self.pause_start_time = Some(WallTime::now());
+        self.changes.send_modify(|_| {});
     }
 
     /// Resumes the simulation clock from a paused state. This method calculates the total duration of the pause
     /// and adds it to the total paused time, allowing the simulation to continue from where it left off.
     fn resume(&mut self) {
-        self.paused_time += WallTime::now() - self.pause_start_time.unwrap_or(WallTime::now());
+        self.paused_time += WallTime::now() - self.pause_start_time.unwrap_or_default();
         self.state = ClockState::Running;
         self.pause_start_time = None;
+        self.changes.send_modify(|_| {});
     }
 
     /// Stops the simulation clock. This is similar to pausing but intended to signal a more permanent halt.
@@ -129,6 +296,7 @@ impl SimClock for RealTimeSimClock {
     fn stop(&mut self) {
         self.pause_start_time = Some(WallTime::now());
         self.state = ClockState::Stopped;
+        self.changes.send_modify(|_| {});
     }
 
     /// Calculates the elapsed time since the simulation started, accounting for any paused duration.
@@ -166,8 +334,8 @@ impl SimClock for RealTimeSimClock {
 
 #[cfg(test)]
 mod rt_clock_tests {
-    use crate::time::real_time_sim_clock::RealTimeSimClock;
-    use crate::time::{Clock, SimClock};
+    use crate::time::real_time_sim_clock::{ClockSnapshot, RealTimeSimClock};
+    use crate::time::{Clock, ClockState, SimClock, SimTime, TimeDuration, WallTime};
     use std::thread::sleep;
 
     #[test]
@@ -186,6 +354,103 @@ mod rt_clock_tests {
         assert_eq!(before, clock.now());
     }
 
+    #[test]
+    pub fn time_dilation_change_keeps_time_continuous() {
+        let mut clock = RealTimeSimClock::default();
+        clock.start(WallTime::now(), SimTime::zero(), TimeDuration::zero(), 1.0);
+        clock.resume();
+        sleep(core::time::Duration::from_millis(20));
+        clock.pause();
+        let before = clock.now();
+        clock.set_time_dilation(100.0).unwrap();
+        assert_eq!(before, clock.now());
+
+        clock.resume();
+        sleep(core::time::Duration::from_millis(20));
+        clock.pause();
+        assert!(clock.now() - before >= 2_000);
+        assert_eq!(
+            clock.delay_time(clock.now() + TimeDuration::milliseconds(10_000)),
+            TimeDuration::milliseconds(100)
+        );
+    }
+
+    #[test]
+    pub fn invalid_time_dilation_is_rejected() {
+        let mut clock = RealTimeSimClock::default();
+        for time_dilation in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(clock.set_time_dilation(time_dilation).is_err());
+        }
+        assert_eq!(clock.time_dilation(), 1.0);
+    }
+
+    #[test]
+    pub fn restored_snapshot_matches_original_clock() {
+        let mut clock = RealTimeSimClock::default();
+        clock.start(
+            WallTime::now(),
+            SimTime::from_seconds(50),
+            TimeDuration::milliseconds(10),
+            1.0,
+        );
+        clock.resume();
+        sleep(core::time::Duration::from_millis(20));
+        clock.set_time_dilation(3.0).unwrap();
+        clock.pause();
+
+        let encoded = serde_json::to_string(&clock.snapshot().unwrap()).unwrap();
+        let snapshot: ClockSnapshot = serde_json::from_str(&encoded).unwrap();
+        let mut restored = RealTimeSimClock::default();
+        restored.restore(&snapshot).unwrap();
+
+        assert!(restored.is_paused());
+        assert_eq!(restored.now(), clock.now());
+        assert_eq!(restored.now(), snapshot.sim_time);
+        assert_eq!(restored.elapsed(), clock.elapsed());
+        assert_eq!(restored.time_dilation(), 3.0);
+        assert_eq!(restored.snapshot().unwrap(), clock.snapshot().unwrap());
+    }
+
+    #[test]
+    pub fn invalid_snapshot_leaves_clock_unchanged() {
+        let mut clock = RealTimeSimClock::default();
+        let mut snapshot = clock.snapshot().unwrap();
+        snapshot.time_dilation = 0.0;
+        snapshot.state = ClockState::Running;
+        assert!(clock.restore(&snapshot).is_err());
+        assert!(clock.is_stopped());
+    }
+
+    #[test]
+    pub fn snapshot_rejects_durations_beyond_nanosecond_range() {
+        let clock = RealTimeSimClock {
+            paused_time: TimeDuration::milliseconds(i64::MAX / 1_000),
+            ..Default::default()
+        };
+        assert!(clock.snapshot().is_err());
+    }
+
+    #[test]
+    pub fn seek_moves_clock_in_both_directions() {
+        let mut clock = RealTimeSimClock::default();
+        clock.start(
+            WallTime::now(),
+            SimTime::from_seconds(10),
+            TimeDuration::zero(),
+            2.0,
+        );
+        clock.seek(SimTime::from_seconds(100));
+        assert_eq!(clock.now(), SimTime::from_seconds(100));
+        assert_eq!(clock.elapsed(), 90_000);
+        clock.seek(SimTime::from_seconds(20));
+        assert_eq!(clock.now(), SimTime::from_seconds(20));
+        assert!(clock.is_paused());
+
+        clock.resume();
+        sleep(core::time::Duration::from_millis(10));
+        assert!(clock.now() - SimTime::from_seconds(20) >= 20);
+    }
+
     #[test]
     pub fn paused_clock_returns_same_time() {
         let mut clock = RealTimeSimClock::default();
@@ -1,4 +1,5 @@
 use crate::time::real_time::TimeDuration;
+use crate::time::TickTime;
 use std::{
     cmp::Ordering,
     fmt::{Display, Formatter},
@@ -38,6 +39,28 @@ impl SimDuration {
     pub fn num_milliseconds(&self) -> i64 {
         self.0.num_milliseconds()
     }
+
+    /// Returns the number of microseconds, or `i64::MAX` on overflow
+    pub fn num_microseconds(&self) -> i64 {
+        self.0.num_microseconds().unwrap_or(i64::MAX)
+    }
+}
+
+/// Serialized as a number of microseconds. Durations beyond the range of `i64` microseconds
+/// fail to serialize.
+impl serde::Serialize for SimDuration {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let micros = self.0.num_microseconds().ok_or_else(|| {
+            serde::ser::Error::custom("SimDuration does not fit in i64 microseconds")
+        })?;
+        serializer.serialize_i64(micros)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SimDuration {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <i64 as serde::Deserialize>::deserialize(deserializer).map(Self::microseconds)
+    }
 }
 
 impl std::ops::Div<f64> for SimDuration {
@@ -65,7 +88,9 @@ impl PartialOrd<i64> for SimDuration {
 /// This time is represented internal as a zero based microsecond offset
 /// from the simulation start. It accounts for pauses and stops in the
 /// simulation.
-#[derive(Copy, Clone, Eq, PartialEq, Debug, PartialOrd, Ord)]
+#[derive(
+    Copy, Clone, Eq, PartialEq, Debug, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
 pub struct SimTime(
     //microseconds
     u64,
@@ -106,6 +131,12 @@ impl SimTime {
     }
 }
 
+impl TickTime for SimTime {
+    fn tick(&self) -> u64 {
+        self.as_millis()
+    }
+}
+
 impl std::ops::Sub for SimTime {
     type Output = SimDuration;
 
@@ -130,17 +161,37 @@ impl std::ops::Add<SimDuration> for SimTime {
     }
 }
 
+impl std::ops::Sub<SimDuration> for SimTime {
+    type Output = SimTime;
+
+    /// Subtracts the duration, saturating at zero.
+    fn sub(self, rhs: SimDuration) -> Self::Output {
+        let duration = rhs
+            .0
+            .num_microseconds()
+            .expect("Duration should not overflow");
+        if duration >= 0 {
+            SimTime(self.0.saturating_sub(duration as u64))
+        } else {
+            SimTime(self.0 + duration.unsigned_abs())
+        }
+    }
+}
+
 impl std::ops::Add<TimeDuration> for SimTime {
     type Output = SimTime;
 
+    /// Adds the duration, saturating at zero for negative durations.
     fn add(self, rhs: TimeDuration) -> Self::Output {
-        SimTime(
-            self.0
-                + rhs
-                    .as_duration()
-                    .num_microseconds()
-                    .expect("TimeDuration should be valid microseconds") as u64,
-        )
+        let micros = rhs
+            .as_duration()
+            .num_microseconds()
+            .expect("TimeDuration should be valid microseconds");
+        if micros >= 0 {
+            SimTime(self.0 + micros as u64)
+        } else {
+            SimTime(self.0.saturating_sub(micros.unsigned_abs()))
+        }
     }
 }
 
@@ -0,0 +1,271 @@
+//! This module contains a simulation clock that only advances when it is explicitly stepped.
+//! It is intended for frame based simulators where a controller decides how far time moves
+//! each frame.
+//!
+use tokio::sync::watch;
+
+use crate::error::ToolboxResult;
+use crate::time::{
+    validate_time_dilation, Clock, ClockChanges, ClockState, DiscreteClock, SimClock, SimDuration,
+    SimTime, TimeDuration, WallTime,
+};
+
+/// `StepSimClock` is a simulation clock whose time only moves when [`StepSimClock::step`] or
+/// [`SimClock::offset_by`] is called.
+pub struct StepSimClock {
+    /// start time of the simulation
+    simulation_start_time: WallTime,
+    /// Reported start time of the simulation. Used to offset the reported time to some arbitrary start time.
+    relative_start_time: SimTime,
+    /// Current simulation time of the clock.
+    current_time: SimTime,
+    /// Expected ratio of simulation time to wall clock time between steps. Only used to estimate
+    /// delay times. Value must be > 0.
+    time_dilation: f64,
+    /// Current state of the clock.
+    state: ClockState,
+    /// Announces steps and state changes to subscribed schedulers.
+    changes: watch::Sender<u64>,
+}
+
+impl StepSimClock {
+    /// Return the simulation start value passed from the controller
+    pub fn simulation_start_time(&self) -> WallTime {
+        self.simulation_start_time
+    }
+
+    /// Advance the clock by a single time step. Steps are ignored unless the clock is running.
+    ///
+    /// # Arguments
+    /// * `by` - The amount of simulation time to advance.
+    ///
+    /// # Returns
+    /// The simulation time after the step has been applied.
+    pub fn step(&mut self, by: SimDuration) -> SimTime {
+        if self.is_running() {
+            self.current_time = self.current_time + by;
+            self.changes.send_modify(|_| {});
+        }
+        self.current_time
+    }
+}
+
+impl Default for StepSimClock {
+    fn default() -> Self {
+        Self {
+            simulation_start_time: WallTime::now(),
+            relative_start_time: SimTime::from_seconds(0),
+            current_time: SimTime::from_seconds(0),
+            time_dilation: 1.0,
+            state: ClockState::Stopped,
+            changes: watch::channel(0).0,
+        }
+    }
+}
+
+impl Clock for StepSimClock {
+    type Time = SimTime;
+    fn now(&self) -> Self::Time {
+        self.current_time
+    }
+
+    /// Estimates the wall clock delay for a future event assuming the controller steps the clock
+    /// at the configured time dilation. The clock itself never advances on its own, so this is
+    /// only a hint to the scheduler.
+    ///
+    /// # Arguments
+    /// * `then` - The future simulation time at which an event is scheduled to occur.
+    fn delay_time(&self, then: Self::Time) -> TimeDuration {
+        let delta = (then - self.now()) / self.time_dilation;
+        if delta > 0 {
+            TimeDuration::milliseconds(delta.num_milliseconds())
+        } else {
+            TimeDuration::zero()
+        }
+    }
+
+    fn changes(&self) -> Option<ClockChanges> {
+        Some(self.changes.subscribe())
+    }
+
+    fn is_halted(&self) -> bool {
+        !self.is_running()
+    }
+
+    /// A single step may pass several occurrences of a recurring event, and each of them fires,
+    /// as with [`ManualClock`](crate::time::ManualClock).
+    fn fires_missed_occurrences(&self) -> bool {
+        true
+    }
+}
+
+impl DiscreteClock for StepSimClock {
+    /// Steps the clock forward to `time`. Times in the past are ignored.
+    fn jump_to(&mut self, time: Self::Time) {
+        if time > self.current_time {
+            self.step(time - self.current_time);
+        }
+    }
+}
+
+impl SimClock for StepSimClock {
+    /// Initializes or resets the clock to `relative_start_time`. The elapsed pause time has no
+    /// effect as paused time never advances this clock. This clock is created paused.
+    fn start(
+        &mut self,
+        simulation_start_time: WallTime,
+        relative_start_time: SimTime,
+        _elapsed_pause_time: TimeDuration,
+        time_dilation: f64,
+    ) {
+        self.simulation_start_time = simulation_start_time;
+        self.relative_start_time = relative_start_time;
+        self.current_time = relative_start_time;
+        self.time_dilation = time_dilation;
+        self.state = ClockState::Paused;
+        self.changes.send_modify(|_| {});
+    }
+
+    /// Moves the clock forwards (positive) or backwards (negative) by the specified duration,
+    /// regardless of the clock state. The clock never moves before the relative start time.
+    /// Moving backwards is announced as a seek, so that schedulers re-arm recurring events.
+    ///
+    /// # Arguments
+    /// * `by` - The duration to move the clock.
+    fn offset_by(&mut self, by: TimeDuration) {
+        let micros = by
+            .as_duration()
+            .num_microseconds()
+            .expect("TimeDuration should be valid microseconds");
+        let current = self.current_time.as_micros() as i64 + micros;
+        let previous = self.current_time;
+        self.current_time =
+            SimTime::from_micros(current.max(0) as u64).max(self.relative_start_time);
+        let backwards = self.current_time < previous;
+        self.changes
+            .send_modify(|seeks| *seeks += u64::from(backwards));
+    }
+
+    /// Changes the time dilation used to estimate delay times. The current time is unaffected.
+    fn set_time_dilation(&mut self, time_dilation: f64) -> ToolboxResult {
+        self.time_dilation = validate_time_dilation(time_dilation)?;
+        self.changes.send_modify(|_| {});
+        Ok(())
+    }
+
+    /// Moves the clock to `to` regardless of the clock state.
+    fn seek(&mut self, to: SimTime) {
+        self.current_time = to;
+        self.changes.send_modify(|seeks| *seeks += 1);
+    }
+
+    /// Pauses the simulation clock. Steps are ignored until `resume` is called.
+    fn pause(&mut self) {
+        self.state = ClockState::Paused;
+        self.changes.send_modify(|_| {});
+    }
+
+    /// Resumes the simulation clock so that steps are applied again.
+    fn resume(&mut self) {
+        self.state = ClockState::Running;
+        self.changes.send_modify(|_| {});
+    }
+
+    /// Stops the simulation clock. Steps are ignored and the current time is retained.
+    fn stop(&mut self) {
+        self.state = ClockState::Stopped;
+        self.changes.send_modify(|_| {});
+    }
+
+    /// Calculates the amount of simulation time stepped since the clock was started.
+    fn elapsed(&self) -> SimDuration {
+        self.now() - self.relative_start_time
+    }
+
+    fn is_paused(&self) -> bool {
+        self.state == ClockState::Paused
+    }
+
+    fn is_running(&self) -> bool {
+        self.state == ClockState::Running
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.state == ClockState::Stopped
+    }
+}
+
+#[cfg(test)]
+mod step_clock_tests {
+    use crate::time::step_sim_clock::StepSimClock;
+    use crate::time::{Clock, SimClock, SimDuration, SimTime, TimeDuration, WallTime};
+
+    fn started_clock() -> StepSimClock {
+        let mut clock = StepSimClock::default();
+        clock.start(
+            WallTime::now(),
+            SimTime::from_seconds(10),
+            TimeDuration::zero(),
+            1.0,
+        );
+        clock
+    }
+
+    #[test]
+    pub fn clock_starts_in_stopped_state() {
+        let clock = StepSimClock::default();
+        assert!(clock.is_stopped());
+    }
+
+    #[test]
+    pub fn start_leaves_clock_paused_at_relative_start() {
+        let clock = started_clock();
+        assert!(clock.is_paused());
+        assert_eq!(clock.now(), SimTime::from_seconds(10));
+        assert_eq!(clock.elapsed(), 0);
+    }
+
+    #[test]
+    pub fn step_advances_only_while_running() {
+        let mut clock = started_clock();
+        clock.step(SimDuration::seconds(1));
+        assert_eq!(clock.now(), SimTime::from_seconds(10));
+
+        clock.resume();
+        assert_eq!(
+            clock.step(SimDuration::seconds(1)),
+            SimTime::from_seconds(11)
+        );
+        assert_eq!(clock.elapsed(), 1_000);
+
+        clock.pause();
+        clock.step(SimDuration::seconds(1));
+        assert_eq!(clock.now(), SimTime::from_seconds(11));
+
+        clock.stop();
+        clock.step(SimDuration::seconds(1));
+        assert_eq!(clock.now(), SimTime::from_seconds(11));
+    }
+
+    #[test]
+    pub fn offset_by_moves_clock_in_both_directions() {
+        let mut clock = started_clock();
+        clock.offset_by(TimeDuration::milliseconds(1_500));
+        assert_eq!(clock.now(), SimTime::from_millis(11_500));
+        clock.offset_by(TimeDuration::milliseconds(-500));
+        assert_eq!(clock.now(), SimTime::from_seconds(11));
+        clock.offset_by(TimeDuration::milliseconds(-5_000));
+        assert_eq!(clock.now(), SimTime::from_seconds(10));
+    }
+
+    #[test]
+    pub fn delay_time_uses_time_dilation() {
+        let mut clock = StepSimClock::default();
+        clock.start(WallTime::now(), SimTime::zero(), TimeDuration::zero(), 2.0);
+        assert_eq!(
+            clock.delay_time(SimTime::from_seconds(2)),
+            TimeDuration::milliseconds(1_000)
+        );
+        assert_eq!(clock.delay_time(SimTime::zero()), TimeDuration::zero());
+    }
+}
@@ -0,0 +1,113 @@
+//! This module contains a clock that reports real UTC wall clock time.
+//!
+use crate::time::{Clock, TimeDuration, WallTime};
+
+/// `SystemWallClock` reads the system wall clock so that schedulers can run events at real UTC
+/// times.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemWallClock;
+
+impl SystemWallClock {
+    /// Create a clock reading the system wall clock.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Clock for SystemWallClock {
+    type Time = WallTime;
+
+    fn now(&self) -> Self::Time {
+        WallTime::now()
+    }
+
+    /// Calculates the wall clock time remaining until `then`, or zero if `then` has passed.
+    ///
+    /// # Arguments
+    /// * `then` - The wall clock time to wait until.
+    fn delay_time(&self, then: Self::Time) -> TimeDuration {
+        let delta = then - self.now();
+        if delta > TimeDuration::zero() {
+            delta
+        } else {
+            TimeDuration::zero()
+        }
+    }
+}
+
+#[cfg(test)]
+mod wall_clock_tests {
+    use std::{cmp::Ordering, sync::Arc};
+
+    use tokio::sync::{mpsc, RwLock};
+
+    use crate::scheduler::event::Event;
+    use crate::scheduler::internal::SchedulerHandle;
+    use crate::time::wall_clock::SystemWallClock;
+    use crate::time::{Clock, TimeDuration, WallTime};
+
+    #[derive(PartialEq, Eq)]
+    struct WallEvent(WallTime);
+
+    impl PartialOrd for WallEvent {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for WallEvent {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    impl Event<SystemWallClock> for WallEvent {
+        fn name(&self) -> &str {
+            "wall"
+        }
+
+        fn execution_time(&self) -> WallTime {
+            self.0
+        }
+
+        fn next_time(&self) -> Self {
+            WallEvent(self.0)
+        }
+
+        fn count(&self, _new_count: u64) -> Self {
+            WallEvent(self.0)
+        }
+    }
+
+    #[test]
+    pub fn delay_time_is_remaining_wall_time() {
+        let clock = SystemWallClock::new();
+        let mut then = clock.now();
+        then += TimeDuration::milliseconds(60_000);
+        let delay = clock.delay_time(then);
+        assert!(delay <= TimeDuration::milliseconds(60_000));
+        assert!(delay > TimeDuration::milliseconds(59_000));
+
+        let past = WallTime::from_timestamp_millis(0).unwrap();
+        assert_eq!(clock.delay_time(past), TimeDuration::zero());
+    }
+
+    #[tokio::test]
+    async fn scheduler_fires_at_wall_time() {
+        let clock = Arc::new(RwLock::new(SystemWallClock::new()));
+        let (sender, mut notifications) = mpsc::unbounded_channel();
+        let handle = SchedulerHandle::new(clock, sender);
+
+        let mut then = WallTime::now();
+        then += TimeDuration::milliseconds(50);
+        handle.schedule(WallEvent(then)).await.unwrap();
+
+        let notification =
+            tokio::time::timeout(tokio::time::Duration::from_secs(1), notifications.recv())
+                .await
+                .expect("event should fire at its wall time")
+                .unwrap();
+        assert_eq!(notification.time(), then);
+        assert!(WallTime::now() >= then);
+    }
+}